@@ -1,12 +1,16 @@
 use std::convert::TryFrom;
-use std::ffi::{CString, c_void};
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::mem;
 use std::ptr;
+use std::time::{Duration, Instant};
 
 use libffi::middle::{Arg, Cif, CodePtr, Type};
 use mlua::prelude::*;
 
-use crate::signature::{CType, Signature};
-use crate::types::{self, TypeCode};
+use crate::error;
+use crate::native;
+use crate::signature::{CType, ResultMode, Signature};
+use crate::types::{self, Overflow, TypeCode};
 
 #[derive(Debug)]
 enum ArgValue {
@@ -41,13 +45,61 @@ impl ArgValue {
     }
 }
 
+// keeps string and buffer arguments alive until the call returns; borrowed strings point
+// straight into the Lua string, which is already NUL-terminated
+#[derive(Default)]
+struct StringArgs {
+    borrow: bool,
+    copies: Vec<CString>,
+    borrowed: Vec<LuaString>,
+    buffers: Vec<LuaBuffer>,
+}
+
+impl StringArgs {
+    fn new(borrow: bool) -> Self {
+        Self {
+            borrow,
+            ..Self::default()
+        }
+    }
+
+    fn pointer_to(&mut self, s: LuaString) -> LuaResult<*mut c_void> {
+        if self.borrow {
+            let bytes = s.as_bytes_with_nul();
+            if let Some((&0, body)) = bytes.split_last() {
+                if !body.contains(&0) {
+                    let ptr = bytes.as_ptr() as *mut c_void;
+                    drop(bytes);
+                    self.borrowed.push(s);
+                    return Ok(ptr);
+                }
+            }
+        }
+
+        let owned = CString::new(s.as_bytes().as_ref())
+            .map_err(|_| error::type_error("string argument contains NUL byte"))?;
+        let ptr = owned.as_ptr() as *mut c_void;
+        self.copies.push(owned);
+        Ok(ptr)
+    }
+
+    // buffers are always borrowed: C sees (and may write to) the buffer's own storage
+    fn pointer_to_buffer(&mut self, value: LuaValue) -> *mut c_void {
+        let ptr = value.to_pointer() as *mut c_void;
+        if let LuaValue::Buffer(buffer) = value {
+            self.buffers.push(buffer);
+        }
+        ptr
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
-struct CDataInfo {
-    ptr: Option<*mut c_void>,
-    type_code: Option<TypeCode>,
+pub(crate) struct CDataInfo {
+    pub(crate) ptr: Option<*mut c_void>,
+    pub(crate) type_code: Option<TypeCode>,
 }
 
-fn extract_cdata_info(table: &LuaTable) -> LuaResult<Option<CDataInfo>> {
+pub(crate) fn extract_cdata_info(table: &LuaTable) -> LuaResult<Option<CDataInfo>> {
     let marker = table.raw_get::<LuaValue>("__ffi_cdata")?;
     if !matches!(marker, LuaValue::Boolean(true)) {
         return Ok(None);
@@ -55,10 +107,10 @@ fn extract_cdata_info(table: &LuaTable) -> LuaResult<Option<CDataInfo>> {
 
     let ptr_value = table.raw_get::<LuaValue>("__ptr")?;
     let ptr = match ptr_value {
-        LuaValue::LightUserData(ptr) => Some(ptr.0),
+        LuaValue::LightUserData(ptr) => Some(ptr.0.wrapping_byte_add(native::cdata_offset(table)?)),
         LuaValue::Nil => None,
         other => {
-            return Err(LuaError::runtime(format!(
+            return Err(error::type_error(format!(
                 "cdata object missing native pointer (found {other:?})",
             )));
         }
@@ -86,14 +138,14 @@ fn extract_cdata_info(table: &LuaTable) -> LuaResult<Option<CDataInfo>> {
                 }
                 LuaValue::Nil => None,
                 other => {
-                    return Err(LuaError::runtime(format!(
+                    return Err(error::type_error(format!(
                         "cdata descriptor missing string code (found {other:?})",
                     )));
                 }
             }
         }
         other => {
-            return Err(LuaError::runtime(format!(
+            return Err(error::type_error(format!(
                 "cdata object has invalid __ctype field (found {other:?})",
             )));
         }
@@ -111,129 +163,321 @@ fn extract_cdata_pointer(table: &LuaTable) -> LuaResult<Option<*mut c_void>> {
     Ok(Some(info.ptr.unwrap_or(std::ptr::null_mut())))
 }
 
+// guard for wrappers handed cdata by untrusted code: the value's type must resolve to
+// the same code as `expected`; its pointer comes back, nil when the cdata is null
+pub(crate) fn assert_cdata_type(
+    value: &LuaValue,
+    expected: &str,
+) -> LuaResult<Option<*mut c_void>> {
+    let expected_code = TypeCode::from_code(&types::normalize_code(expected))?;
+    let info = match value {
+        LuaValue::Table(table) => extract_cdata_info(table)?,
+        _ => None,
+    };
+    let Some(info) = info else {
+        return Err(error::type_error(format!(
+            "expected {} cdata, got {}",
+            expected_code.name(),
+            value.type_name()
+        )));
+    };
+
+    match info.type_code {
+        Some(code) if code == expected_code => Ok(info.ptr),
+        Some(code) => Err(error::type_error(format!(
+            "expected {} cdata, got {} cdata",
+            expected_code.name(),
+            code.name()
+        ))),
+        None => Err(error::type_error(format!(
+            "expected {} cdata, got cdata of a non-primitive type",
+            expected_code.name()
+        ))),
+    }
+}
+
 fn convert_cdata_variadic_argument(
     info: CDataInfo,
     original_type: TypeCode,
 ) -> LuaResult<(ArgValue, TypeCode)> {
-    let ptr = info.ptr.ok_or_else(|| {
-        LuaError::runtime("cdata value missing native storage pointer".to_string())
-    })?;
+    let ptr = info
+        .ptr
+        .ok_or_else(|| error::null_pointer("cdata value missing native storage pointer"))?;
 
     unsafe {
         match original_type {
-            TypeCode::Void => Err(LuaError::runtime(
-                "void type cannot be used as a variadic argument".to_string(),
+            TypeCode::Void => Err(error::type_error(
+                "void type cannot be used as a variadic argument",
             )),
             TypeCode::Int8 => {
-                let raw = ptr::read(ptr as *const i8);
+                let raw = ptr::read_unaligned(ptr as *const i8);
                 Ok((ArgValue::Int32(raw as i32), TypeCode::Int32))
             }
-            TypeCode::UInt8 => {
-                let raw = ptr::read(ptr as *const u8);
+            TypeCode::Bool | TypeCode::UInt8 => {
+                let raw = ptr::read_unaligned(ptr as *const u8);
                 Ok((ArgValue::Int32(raw as i32), TypeCode::Int32))
             }
             TypeCode::Int16 => {
-                let raw = ptr::read(ptr as *const i16);
+                let raw = ptr::read_unaligned(ptr as *const i16);
                 Ok((ArgValue::Int32(raw as i32), TypeCode::Int32))
             }
             TypeCode::UInt16 => {
-                let raw = ptr::read(ptr as *const u16);
+                let raw = ptr::read_unaligned(ptr as *const u16);
                 Ok((ArgValue::Int32(raw as i32), TypeCode::Int32))
             }
             TypeCode::Int32 => {
-                let raw = ptr::read(ptr as *const i32);
+                let raw = ptr::read_unaligned(ptr as *const i32);
                 Ok((ArgValue::Int32(raw), TypeCode::Int32))
             }
             TypeCode::UInt32 => {
-                let raw = ptr::read(ptr as *const u32);
+                let raw = ptr::read_unaligned(ptr as *const u32);
                 Ok((ArgValue::UInt32(raw), TypeCode::UInt32))
             }
             TypeCode::Int64 => {
-                let raw = ptr::read(ptr as *const i64);
+                let raw = ptr::read_unaligned(ptr as *const i64);
                 Ok((ArgValue::Int64(raw), TypeCode::Int64))
             }
             TypeCode::UInt64 => {
-                let raw = ptr::read(ptr as *const u64);
+                let raw = ptr::read_unaligned(ptr as *const u64);
                 Ok((ArgValue::UInt64(raw), TypeCode::UInt64))
             }
             TypeCode::IntPtr => {
                 if cfg!(target_pointer_width = "64") {
-                    let raw = ptr::read(ptr as *const i64);
+                    let raw = ptr::read_unaligned(ptr as *const i64);
                     Ok((ArgValue::Int64(raw), TypeCode::IntPtr))
                 } else {
-                    let raw = ptr::read(ptr as *const i32);
+                    let raw = ptr::read_unaligned(ptr as *const i32);
                     Ok((ArgValue::Int32(raw), TypeCode::IntPtr))
                 }
             }
             TypeCode::UIntPtr => {
                 if cfg!(target_pointer_width = "64") {
-                    let raw = ptr::read(ptr as *const u64);
+                    let raw = ptr::read_unaligned(ptr as *const u64);
                     Ok((ArgValue::UInt64(raw), TypeCode::UIntPtr))
                 } else {
-                    let raw = ptr::read(ptr as *const u32);
+                    let raw = ptr::read_unaligned(ptr as *const u32);
                     Ok((ArgValue::UInt32(raw), TypeCode::UIntPtr))
                 }
             }
             TypeCode::Float32 => {
-                let raw = ptr::read(ptr as *const f32);
+                let raw = ptr::read_unaligned(ptr as *const f32);
                 Ok((ArgValue::Float64(raw as f64), TypeCode::Float64))
             }
             TypeCode::Float64 => {
-                let raw = ptr::read(ptr as *const f64);
+                let raw = ptr::read_unaligned(ptr as *const f64);
                 Ok((ArgValue::Float64(raw), TypeCode::Float64))
             }
             TypeCode::Pointer => Ok((
-                ArgValue::Pointer(ptr::read(ptr as *const *mut c_void)),
+                ArgValue::Pointer(ptr::read_unaligned(ptr as *const *mut c_void)),
                 TypeCode::Pointer,
             )),
+            TypeCode::LongDouble => Err(types::long_double_unsupported()),
+            TypeCode::Struct => Err(error::type_error(
+                "TODO(@lune/ffi/call): struct values cannot be passed as variadic arguments yet",
+            )),
         }
     }
 }
 
+fn is_float_code(code: TypeCode) -> bool {
+    matches!(code, TypeCode::Float32 | TypeCode::Float64)
+}
+
+fn is_integer_code(code: TypeCode) -> bool {
+    matches!(
+        code,
+        TypeCode::Bool
+            | TypeCode::Int8
+            | TypeCode::UInt8
+            | TypeCode::Int16
+            | TypeCode::UInt16
+            | TypeCode::Int32
+            | TypeCode::UInt32
+            | TypeCode::Int64
+            | TypeCode::UInt64
+            | TypeCode::IntPtr
+            | TypeCode::UIntPtr
+    )
+}
+
+// reads the value boxed by a scalar cdata so it can be passed where the signature
+// expects a scalar; returns None when the table is not cdata at all
+fn load_cdata_scalar(table: &LuaTable, target: TypeCode) -> LuaResult<Option<ArgValue>> {
+    let Some(info) = extract_cdata_info(table)? else {
+        return Ok(None);
+    };
+    let source = info.type_code.ok_or_else(|| {
+        error::type_error(format!(
+            "cannot pass cdata of unknown type as {target:?} argument"
+        ))
+    })?;
+    let compatible = (is_integer_code(source) && is_integer_code(target))
+        || (is_float_code(source) && is_float_code(target));
+    if !compatible {
+        return Err(error::type_error(format!(
+            "cannot pass cdata<{source:?}> as {target:?} argument"
+        )));
+    }
+
+    let (stored, _) = convert_cdata_variadic_argument(info, source)?;
+    Ok(Some(stored))
+}
+
+// escape hatch for types the crate does not model: a struct or opaque cdata whose size
+// matches the target primitive hands over its raw bytes unconverted
+fn reinterpret_cdata_argument(
+    table: &LuaTable,
+    info: CDataInfo,
+    target: TypeCode,
+) -> LuaResult<ArgValue> {
+    let size = match table.raw_get::<Option<LuaTable>>("__ctype")? {
+        Some(ctype) => ctype.raw_get::<Option<usize>>("size")?,
+        None => None,
+    };
+    let Some(size) = size else {
+        return Err(error::type_error(format!(
+            "cannot pass cdata of unknown type as {target:?} argument"
+        )));
+    };
+    if size != target.size_of() {
+        return Err(error::range(format!(
+            "cannot reinterpret {size}-byte cdata as {} argument of {} byte(s)",
+            target.name(),
+            target.size_of()
+        )));
+    }
+    let ptr = info
+        .ptr
+        .filter(|ptr| !ptr.is_null())
+        .ok_or_else(|| error::null_pointer("cdata value missing native storage pointer"))?;
+
+    let value = unsafe {
+        match target {
+            TypeCode::Int8 => ArgValue::Int8(ptr::read_unaligned(ptr as *const i8)),
+            TypeCode::Bool | TypeCode::UInt8 => {
+                ArgValue::UInt8(ptr::read_unaligned(ptr as *const u8))
+            }
+            TypeCode::Int16 => ArgValue::Int16(ptr::read_unaligned(ptr as *const i16)),
+            TypeCode::UInt16 => ArgValue::UInt16(ptr::read_unaligned(ptr as *const u16)),
+            TypeCode::Int32 => ArgValue::Int32(ptr::read_unaligned(ptr as *const i32)),
+            TypeCode::UInt32 => ArgValue::UInt32(ptr::read_unaligned(ptr as *const u32)),
+            TypeCode::Int64 => ArgValue::Int64(ptr::read_unaligned(ptr as *const i64)),
+            TypeCode::UInt64 => ArgValue::UInt64(ptr::read_unaligned(ptr as *const u64)),
+            TypeCode::IntPtr if cfg!(target_pointer_width = "64") => {
+                ArgValue::Int64(ptr::read_unaligned(ptr as *const i64))
+            }
+            TypeCode::IntPtr => ArgValue::Int32(ptr::read_unaligned(ptr as *const i32)),
+            TypeCode::UIntPtr if cfg!(target_pointer_width = "64") => {
+                ArgValue::UInt64(ptr::read_unaligned(ptr as *const u64))
+            }
+            TypeCode::UIntPtr => ArgValue::UInt32(ptr::read_unaligned(ptr as *const u32)),
+            TypeCode::Float32 => ArgValue::Float32(ptr::read_unaligned(ptr as *const f32)),
+            TypeCode::Float64 => ArgValue::Float64(ptr::read_unaligned(ptr as *const f64)),
+            other => {
+                return Err(error::type_error(format!(
+                    "cannot reinterpret cdata as {} argument",
+                    other.name()
+                )));
+            }
+        }
+    };
+    Ok(value)
+}
+
 fn convert_typed_argument(
     value: LuaValue,
     ty: &CType,
-    string_refs: &mut Vec<CString>,
+    overflow: Overflow,
+    string_refs: &mut StringArgs,
 ) -> LuaResult<(ArgValue, TypeCode)> {
+    let code = ty.code();
+    if let LuaValue::Table(table) = &value {
+        let scalar_target = is_integer_code(code) || is_float_code(code);
+        if scalar_target
+            && let Some(info) = extract_cdata_info(table)?
+            && matches!(info.type_code, None | Some(TypeCode::Struct))
+        {
+            return Ok((reinterpret_cdata_argument(table, info, code)?, code));
+        }
+        let stored = if scalar_target {
+            load_cdata_scalar(table, code)?
+        } else {
+            None
+        };
+        if let Some(stored) = stored {
+            let loaded = match stored {
+                ArgValue::Int32(v) => LuaValue::Integer(v.into()),
+                ArgValue::UInt32(v) => LuaValue::Integer(v.into()),
+                ArgValue::Int64(v) => LuaValue::Integer(v),
+                ArgValue::UInt64(v) if v <= i64::MAX as u64 => LuaValue::Integer(v as i64),
+                ArgValue::UInt64(v) => {
+                    let wide = code == TypeCode::UInt64
+                        || (code == TypeCode::UIntPtr && usize::BITS == 64);
+                    if wide {
+                        return Ok((ArgValue::UInt64(v), code));
+                    }
+                    if overflow == Overflow::Error {
+                        return Err(error::range(format!(
+                            "value {v} out of range for {}",
+                            code.name()
+                        )));
+                    }
+                    LuaValue::Integer(v as i64)
+                }
+                ArgValue::Float64(v) => LuaValue::Number(v),
+                other => {
+                    return Err(error::type_error(format!(
+                        "cannot pass cdata value {other:?} as {code:?} argument"
+                    )));
+                }
+            };
+            return convert_typed_argument(loaded, ty, overflow, string_refs);
+        }
+    }
+
     match ty.code() {
-        TypeCode::Void => Err(LuaError::runtime(
-            "void type cannot be used as a function argument".to_string(),
+        TypeCode::Void => Err(error::type_error(
+            "void type cannot be used as a function argument",
         )),
         TypeCode::Int8 => {
-            let v = types::clamp_signed(types::lua_value_to_i64(&value)?, 8)? as i8;
+            let v = types::to_signed(&value, 8, overflow)? as i8;
             Ok((ArgValue::Int8(v), TypeCode::Int8))
         }
+        TypeCode::Bool => {
+            let v = types::lua_value_to_bool(&value)?;
+            Ok((ArgValue::UInt8(u8::from(v)), TypeCode::Bool))
+        }
         TypeCode::UInt8 => {
-            let v = types::clamp_unsigned(types::lua_value_to_u64(&value)?, 8)? as u8;
+            let v = types::to_unsigned(&value, 8, overflow)? as u8;
             Ok((ArgValue::UInt8(v), TypeCode::UInt8))
         }
         TypeCode::Int16 => {
-            let v = types::clamp_signed(types::lua_value_to_i64(&value)?, 16)? as i16;
+            let v = types::to_signed(&value, 16, overflow)? as i16;
             Ok((ArgValue::Int16(v), TypeCode::Int16))
         }
         TypeCode::UInt16 => {
-            let v = types::clamp_unsigned(types::lua_value_to_u64(&value)?, 16)? as u16;
+            let v = types::to_unsigned(&value, 16, overflow)? as u16;
             Ok((ArgValue::UInt16(v), TypeCode::UInt16))
         }
         TypeCode::Int32 => {
-            let v = types::clamp_signed(types::lua_value_to_i64(&value)?, 32)? as i32;
+            let v = types::to_signed(&value, 32, overflow)? as i32;
             Ok((ArgValue::Int32(v), TypeCode::Int32))
         }
         TypeCode::UInt32 => {
-            let v = types::clamp_unsigned(types::lua_value_to_u64(&value)?, 32)? as u32;
+            let v = types::to_unsigned(&value, 32, overflow)? as u32;
             Ok((ArgValue::UInt32(v), TypeCode::UInt32))
         }
         TypeCode::Int64 => Ok((
-            ArgValue::Int64(types::lua_value_to_i64(&value)?),
+            ArgValue::Int64(types::to_signed(&value, 64, overflow)?),
             TypeCode::Int64,
         )),
         TypeCode::UInt64 => Ok((
-            ArgValue::UInt64(types::lua_value_to_u64(&value)?),
+            ArgValue::UInt64(types::to_unsigned(&value, 64, overflow)?),
             TypeCode::UInt64,
         )),
         TypeCode::IntPtr => {
             let bits = usize::BITS;
-            let value = types::clamp_signed(types::lua_value_to_i64(&value)?, bits)?;
+            let value = types::to_signed(&value, bits, overflow)?;
             if bits == 64 {
                 Ok((ArgValue::Int64(value), TypeCode::IntPtr))
             } else {
@@ -242,7 +486,7 @@ fn convert_typed_argument(
         }
         TypeCode::UIntPtr => {
             let bits = usize::BITS;
-            let value = types::clamp_unsigned(types::lua_value_to_u64(&value)?, bits)?;
+            let value = types::to_unsigned(&value, bits, overflow)?;
             if bits == 64 {
                 Ok((ArgValue::UInt64(value), TypeCode::UIntPtr))
             } else {
@@ -251,23 +495,29 @@ fn convert_typed_argument(
         }
         TypeCode::Float32 => match value {
             LuaValue::Number(n) => Ok((ArgValue::Float32(n as f32), TypeCode::Float32)),
-            LuaValue::Integer(i) => Ok((ArgValue::Float32(i as f32), TypeCode::Float32)),
+            LuaValue::Integer(i) => Ok((
+                ArgValue::Float32(types::integer_to_f32(i)?),
+                TypeCode::Float32,
+            )),
             LuaValue::Boolean(b) => Ok((
                 ArgValue::Float32(if b { 1.0 } else { 0.0 }),
                 TypeCode::Float32,
             )),
-            other => Err(LuaError::runtime(format!(
+            other => Err(error::type_error(format!(
                 "expected numeric value for float argument, got {other:?}"
             ))),
         },
         TypeCode::Float64 => match value {
             LuaValue::Number(n) => Ok((ArgValue::Float64(n), TypeCode::Float64)),
-            LuaValue::Integer(i) => Ok((ArgValue::Float64(i as f64), TypeCode::Float64)),
+            LuaValue::Integer(i) => Ok((
+                ArgValue::Float64(types::integer_to_f64(i)?),
+                TypeCode::Float64,
+            )),
             LuaValue::Boolean(b) => Ok((
                 ArgValue::Float64(if b { 1.0 } else { 0.0 }),
                 TypeCode::Float64,
             )),
-            other => Err(LuaError::runtime(format!(
+            other => Err(error::type_error(format!(
                 "expected numeric value for double argument, got {other:?}"
             ))),
         },
@@ -276,57 +526,54 @@ fn convert_typed_argument(
             LuaValue::LightUserData(ptr) => Ok((ArgValue::Pointer(ptr.0), TypeCode::Pointer)),
             LuaValue::Table(table) => match extract_cdata_pointer(&table)? {
                 Some(ptr) => Ok((ArgValue::Pointer(ptr), TypeCode::Pointer)),
-                None => Err(LuaError::runtime(
-                    "cannot convert table value to pointer argument".to_string(),
+                None => Err(error::type_error(
+                    "cannot convert table value to pointer argument",
                 )),
             },
             LuaValue::Integer(i) => Ok((
                 ArgValue::Pointer(
-                    usize::try_from(i)
-                        .map_err(|_| LuaError::runtime("negative pointer value".to_string()))?
+                    usize::try_from(i).map_err(|_| error::range("negative pointer value"))?
                         as *mut c_void,
                 ),
                 TypeCode::Pointer,
             )),
             LuaValue::Number(n) => {
                 if !n.is_finite() {
-                    return Err(LuaError::runtime(
-                        "pointer value must be finite".to_string(),
-                    ));
+                    return Err(error::range("pointer value must be finite"));
                 }
                 if n < 0.0 {
-                    return Err(LuaError::runtime(
-                        "pointer value must be non-negative".to_string(),
-                    ));
+                    return Err(error::range("pointer value must be non-negative"));
                 }
                 if (n.trunc() - n).abs() > f64::EPSILON {
-                    return Err(LuaError::runtime(
-                        "pointer value must be integral".to_string(),
-                    ));
+                    return Err(error::type_error("pointer value must be integral"));
                 }
                 Ok((
                     ArgValue::Pointer(n as usize as *mut c_void),
                     TypeCode::Pointer,
                 ))
             }
-            LuaValue::String(s) => {
-                let owned = CString::new(s.as_bytes().as_ref()).map_err(|_| {
-                    LuaError::runtime("string argument contains NUL byte".to_string())
-                })?;
-                let ptr = owned.as_ptr() as *mut c_void;
-                string_refs.push(owned);
-                Ok((ArgValue::Pointer(ptr), TypeCode::Pointer))
-            }
-            other => Err(LuaError::runtime(format!(
+            LuaValue::String(s) => Ok((
+                ArgValue::Pointer(string_refs.pointer_to(s)?),
+                TypeCode::Pointer,
+            )),
+            buffer @ LuaValue::Buffer(_) => Ok((
+                ArgValue::Pointer(string_refs.pointer_to_buffer(buffer)),
+                TypeCode::Pointer,
+            )),
+            other => Err(error::type_error(format!(
                 "cannot convert value {other:?} to pointer argument"
             ))),
         },
+        TypeCode::LongDouble => Err(types::long_double_unsupported()),
+        TypeCode::Struct => Err(error::type_error(
+            "TODO(@lune/ffi/call): struct arguments by value not supported yet",
+        )),
     }
 }
 
 fn convert_variadic_argument(
     value: LuaValue,
-    string_refs: &mut Vec<CString>,
+    string_refs: &mut StringArgs,
 ) -> LuaResult<(ArgValue, TypeCode)> {
     match value {
         LuaValue::Nil => Ok((ArgValue::Pointer(std::ptr::null_mut()), TypeCode::Pointer)),
@@ -345,23 +592,25 @@ fn convert_variadic_argument(
                     return Ok((ArgValue::Pointer(ptr), TypeCode::Pointer));
                 }
 
-                return Err(LuaError::runtime(
-                    "cannot infer C type for variadic cdata argument".to_string(),
+                return Err(error::type_error(
+                    "cannot infer C type for variadic cdata argument",
                 ));
             }
 
-            Err(LuaError::runtime(
-                "cannot infer C type for variadic table argument".to_string(),
+            Err(error::type_error(
+                "cannot infer C type for variadic table argument",
             ))
         }
-        LuaValue::String(s) => {
-            let owned = CString::new(s.as_bytes().as_ref())
-                .map_err(|_| LuaError::runtime("string argument contains NUL byte".to_string()))?;
-            let ptr = owned.as_ptr() as *mut c_void;
-            string_refs.push(owned);
-            Ok((ArgValue::Pointer(ptr), TypeCode::Pointer))
-        }
+        LuaValue::String(s) => Ok((
+            ArgValue::Pointer(string_refs.pointer_to(s)?),
+            TypeCode::Pointer,
+        )),
         LuaValue::Boolean(b) => {
+            if types::is_strict() {
+                return Err(error::type_error(
+                    "booleans cannot be passed as variadic arguments in strict mode; pass 0 or 1",
+                ));
+            }
             let value = if b { 1 } else { 0 };
             Ok((ArgValue::Int32(value), TypeCode::Int32))
         }
@@ -375,39 +624,92 @@ fn convert_variadic_argument(
         }
         LuaValue::Number(n) => {
             if !n.is_finite() {
-                return Err(LuaError::runtime(
-                    "numeric argument must be finite".to_string(),
-                ));
+                return Err(error::range("numeric argument must be finite"));
             }
             Ok((ArgValue::Float64(n), TypeCode::Float64))
         }
-        other => Err(LuaError::runtime(format!(
+        other => Err(error::type_error(format!(
             "cannot infer C type for variadic argument {other:?}"
         ))),
     }
 }
 
 fn convert_argument(
+    position: usize,
     value: LuaValue,
     ty: Option<&CType>,
-    string_refs: &mut Vec<CString>,
+    overflow: Overflow,
+    string_refs: &mut StringArgs,
 ) -> LuaResult<(ArgValue, TypeCode)> {
+    // nil only converts to a null pointer; for anything else it is almost always a
+    // forgotten argument, so say which one
+    if let (LuaValue::Nil, Some(ty)) = (&value, ty) {
+        if ty.code() != TypeCode::Pointer {
+            return Err(error::type_error(format!(
+                "argument {position} expected {} but got nil",
+                ty.code().name()
+            )));
+        }
+    }
+
     match ty {
-        Some(ty) => convert_typed_argument(value, ty, string_refs),
+        Some(ty) => convert_typed_argument(value, ty, overflow, string_refs)
+            .map_err(|err| name_range_error(err, position, ty.code())),
         None => convert_variadic_argument(value, string_refs),
     }
 }
 
+// the clamp helpers only know the value and width; this says which parameter it was
+fn name_range_error(err: LuaError, position: usize, code: TypeCode) -> LuaError {
+    let message = match error::find(&err) {
+        Some(ffi_err) if ffi_err.kind() == error::ErrorKind::Range => {
+            format!(
+                "argument {position} ({}): {}",
+                code.name(),
+                ffi_err.message()
+            )
+        }
+        _ => return err,
+    };
+    error::range(message)
+}
+
+fn promote_variadic(arg: ArgValue, code: TypeCode) -> (ArgValue, TypeCode) {
+    match arg {
+        ArgValue::Int8(value) => (ArgValue::Int32(value.into()), TypeCode::Int32),
+        ArgValue::UInt8(value) => (ArgValue::Int32(value.into()), TypeCode::Int32),
+        ArgValue::Int16(value) => (ArgValue::Int32(value.into()), TypeCode::Int32),
+        ArgValue::UInt16(value) => (ArgValue::Int32(value.into()), TypeCode::Int32),
+        ArgValue::Float32(value) => (ArgValue::Float64(value.into()), TypeCode::Float64),
+        other => (other, code),
+    }
+}
+
 fn collect_arguments(
     args_table: LuaTable,
     signature: &Signature,
-) -> LuaResult<(Vec<ArgValue>, Vec<Type>, Vec<CString>)> {
+    build_types: bool,
+) -> LuaResult<(Vec<ArgValue>, Vec<Type>, StringArgs)> {
     let explicit_n = args_table.get::<Option<u32>>("n")?.map(|n| n as usize);
     let arg_count = explicit_n.unwrap_or_else(|| args_table.raw_len() as usize);
+    let variadic_types = args_table.get::<Option<LuaTable>>("variadicTypes")?;
+    convert_arguments(signature, arg_count, variadic_types, build_types, |index| {
+        args_table.raw_get::<LuaValue>(index as i64 + 1)
+    })
+}
 
+// `build_types` is false when the caller already holds a cif for the fixed argument list,
+// which leaves the returned type list empty
+fn convert_arguments(
+    signature: &Signature,
+    arg_count: usize,
+    variadic_types: Option<LuaTable>,
+    build_types: bool,
+    get: impl Fn(usize) -> LuaResult<LuaValue>,
+) -> LuaResult<(Vec<ArgValue>, Vec<Type>, StringArgs)> {
     if signature.is_variadic() {
         if arg_count < signature.fixed_count() {
-            return Err(LuaError::runtime(format!(
+            return Err(error::type_error(format!(
                 "function expected at least {} argument(s) but received {arg_count}",
                 signature.fixed_count()
             )));
@@ -415,265 +717,2052 @@ fn collect_arguments(
     } else {
         let expected = signature.args().len();
         if arg_count != expected {
-            return Err(LuaError::runtime(format!(
+            return Err(error::type_error(format!(
                 "function expected {expected} argument(s) but received {arg_count}"
             )));
         }
     }
 
     let mut values = Vec::with_capacity(arg_count);
-    let mut arg_types = Vec::with_capacity(arg_count);
-    let mut string_refs = Vec::new();
+    let mut arg_types = Vec::with_capacity(if build_types { arg_count } else { 0 });
+    let mut string_refs = StringArgs::new(signature.borrow_strings());
 
     for index in 0..arg_count {
-        let value = args_table.raw_get::<LuaValue>(index as i64 + 1)?;
+        let value = get(index)?;
         let type_hint = signature.args().get(index);
+        if signature.strict_pointers()
+            && type_hint.is_some_and(|ty| ty.code() == TypeCode::Pointer)
+            && matches!(value, LuaValue::Integer(_) | LuaValue::Number(_))
+        {
+            return Err(error::type_error(format!(
+                "argument {} expects a pointer, got number {} (strictPointers refuses \
+                 numeric addresses; pass cdata or lightuserdata)",
+                index + 1,
+                types::lua_value_to_i64(&value)
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|_| format!("{value:?}"))
+            )));
+        }
 
         if index < signature.fixed_count() {
             let ty = type_hint.ok_or_else(|| {
-                LuaError::runtime(format!(
+                error::type_error(format!(
                     "missing type information for fixed argument {}",
                     index + 1
                 ))
             })?;
 
-            let (arg, _) = convert_argument(value, Some(ty), &mut string_refs)?;
-            arg_types.push(ty.to_libffi_type());
+            let (arg, _) = convert_argument(
+                index + 1,
+                value,
+                Some(ty),
+                signature.overflow(),
+                &mut string_refs,
+            )?;
+            if build_types {
+                arg_types.push(ty.to_libffi_type());
+            }
             values.push(arg);
             continue;
         }
 
         if !signature.is_variadic() {
             let ty = type_hint.ok_or_else(|| {
-                LuaError::runtime(format!(
+                error::type_error(format!(
                     "missing type information for argument {}",
                     index + 1
                 ))
             })?;
-            let (arg, _) = convert_argument(value, Some(ty), &mut string_refs)?;
-            arg_types.push(ty.to_libffi_type());
+            let (arg, _) = convert_argument(
+                index + 1,
+                value,
+                Some(ty),
+                signature.overflow(),
+                &mut string_refs,
+            )?;
+            if build_types {
+                arg_types.push(ty.to_libffi_type());
+            }
             values.push(arg);
             continue;
         }
 
-        let (arg, inferred) = convert_argument(value, type_hint, &mut string_refs)?;
-        let ffi_type = match type_hint {
-            Some(ty) => ty.to_libffi_type(),
-            None => CType { code: inferred }.to_libffi_type(),
+        let tagged = match &variadic_types {
+            Some(types) => match types.raw_get::<LuaValue>(index - signature.fixed_count() + 1)? {
+                LuaValue::Nil => None,
+                ty => Some(CType::from_lua(ty)?),
+            },
+            None => None,
         };
-        arg_types.push(ffi_type);
+        let type_hint = tagged.as_ref().or(type_hint);
+
+        // variadic arguments follow the C default promotions regardless of the declared type
+        let (arg, inferred) = convert_argument(
+            index + 1,
+            value,
+            type_hint,
+            signature.overflow(),
+            &mut string_refs,
+        )?;
+        let (arg, promoted) = promote_variadic(arg, inferred);
+        arg_types.push(CType::primitive(promoted).to_libffi_type());
         values.push(arg);
     }
 
     Ok((values, arg_types, string_refs))
 }
 
+trait CallTimer {
+    fn time<R>(&mut self, call: impl FnOnce() -> R) -> R;
+}
+
+struct Untimed;
+
+impl CallTimer for Untimed {
+    #[inline(always)]
+    fn time<R>(&mut self, call: impl FnOnce() -> R) -> R {
+        call()
+    }
+}
+
+#[derive(Default)]
+struct Stopwatch {
+    elapsed: Duration,
+}
+
+impl CallTimer for Stopwatch {
+    fn time<R>(&mut self, call: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = call();
+        self.elapsed = start.elapsed();
+        result
+    }
+}
+
+// zeroes errno immediately before the foreign call and reads it immediately after,
+// so no marshaling or allocation on either side can clobber the snapshot
+#[derive(Default)]
+struct ErrnoReset {
+    errno: i32,
+}
+
+impl CallTimer for ErrnoReset {
+    fn time<R>(&mut self, call: impl FnOnce() -> R) -> R {
+        native::set_errno(0);
+        let result = call();
+        self.errno = native::get_errno();
+        result
+    }
+}
+
+// reads errno the moment the foreign call returns, inside whatever timer wraps it, so
+// `onError` reports what the function left behind rather than what result conversion
+// (which allocates) may have overwritten since
+struct ErrnoSnapshot<'a, T> {
+    inner: &'a mut T,
+    errno: i32,
+}
+
+impl<'a, T: CallTimer> ErrnoSnapshot<'a, T> {
+    fn new(inner: &'a mut T) -> Self {
+        Self { inner, errno: 0 }
+    }
+}
+
+impl<T: CallTimer> CallTimer for ErrnoSnapshot<'_, T> {
+    fn time<R>(&mut self, call: impl FnOnce() -> R) -> R {
+        let errno = &mut self.errno;
+        self.inner.time(|| {
+            let result = call();
+            *errno = native::get_errno();
+            result
+        })
+    }
+}
+
 fn call_with_signature(
+    timer: &mut impl CallTimer,
+    lua: &Lua,
     signature: &Signature,
+    result_descriptor: &LuaValue,
     func: LuaLightUserData,
-    cif: Cif,
+    cif: &Cif,
     args: &[Arg],
-) -> LuaResult<LuaValue> {
+) -> LuaResult<(LuaValue, i32)> {
     let code_ptr = CodePtr::from_ptr(func.0 as *const c_void);
+    let mut snapshot = ErrnoSnapshot::new(timer);
+    let timer = &mut snapshot;
+    if signature.is_noreturn() {
+        unsafe { timer.time(|| cif.call::<()>(code_ptr, args)) };
+        return Err(noreturn_returned());
+    }
 
-    unsafe {
+    let value = unsafe {
         match signature.result().code() {
             TypeCode::Void => {
-                cif.call::<()>(code_ptr, args);
+                timer.time(|| cif.call::<()>(code_ptr, args));
                 Ok(LuaValue::Nil)
             }
             TypeCode::Int8 => {
-                let value: i8 = cif.call(code_ptr, args);
+                let value: i8 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer(value.into()))
             }
+            TypeCode::Bool => {
+                let value: u8 = timer.time(|| cif.call(code_ptr, args));
+                Ok(LuaValue::Boolean(value != 0))
+            }
             TypeCode::UInt8 => {
-                let value: u8 = cif.call(code_ptr, args);
+                let value: u8 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer((value as i64).into()))
             }
             TypeCode::Int16 => {
-                let value: i16 = cif.call(code_ptr, args);
+                let value: i16 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer(value.into()))
             }
             TypeCode::UInt16 => {
-                let value: u16 = cif.call(code_ptr, args);
+                let value: u16 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer((value as i64).into()))
             }
             TypeCode::Int32 => {
-                let value: i32 = cif.call(code_ptr, args);
+                let value: i32 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer(value.into()))
             }
             TypeCode::UInt32 => {
-                let value: u32 = cif.call(code_ptr, args);
+                let value: u32 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Integer((value as i64).into()))
             }
             TypeCode::Int64 => {
-                let value: i64 = cif.call(code_ptr, args);
-                Ok(LuaValue::Integer(value))
+                let value: i64 = timer.time(|| cif.call(code_ptr, args));
+                wide_result(lua, signature, value as u64)
             }
             TypeCode::UInt64 => {
-                let value: u64 = cif.call(code_ptr, args);
-                if value <= i64::MAX as u64 {
-                    Ok(LuaValue::Integer(value as i64))
-                } else {
-                    Ok(LuaValue::Number(value as f64))
-                }
+                let value: u64 = timer.time(|| cif.call(code_ptr, args));
+                wide_result(lua, signature, value)
             }
             TypeCode::IntPtr => {
                 if cfg!(target_pointer_width = "64") {
-                    let value: i64 = cif.call(code_ptr, args);
-                    Ok(LuaValue::Integer(value))
+                    let value: i64 = timer.time(|| cif.call(code_ptr, args));
+                    wide_result(lua, signature, value as u64)
                 } else {
-                    let value: i32 = cif.call(code_ptr, args);
-                    Ok(LuaValue::Integer(value.into()))
+                    let value: i32 = timer.time(|| cif.call(code_ptr, args));
+                    wide_result(lua, signature, i64::from(value) as u64)
                 }
             }
             TypeCode::UIntPtr => {
                 if cfg!(target_pointer_width = "64") {
-                    let value: u64 = cif.call(code_ptr, args);
-                    if value <= i64::MAX as u64 {
-                        Ok(LuaValue::Integer(value as i64))
-                    } else {
-                        Ok(LuaValue::Number(value as f64))
-                    }
+                    let value: u64 = timer.time(|| cif.call(code_ptr, args));
+                    wide_result(lua, signature, value)
                 } else {
-                    let value: u32 = cif.call(code_ptr, args);
-                    Ok(LuaValue::Integer((value as i64).into()))
+                    let value: u32 = timer.time(|| cif.call(code_ptr, args));
+                    wide_result(lua, signature, value.into())
                 }
             }
             TypeCode::Float32 => {
-                let value: f32 = cif.call(code_ptr, args);
+                let value: f32 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Number(value as f64))
             }
             TypeCode::Float64 => {
-                let value: f64 = cif.call(code_ptr, args);
+                let value: f64 = timer.time(|| cif.call(code_ptr, args));
                 Ok(LuaValue::Number(value))
             }
             TypeCode::Pointer => {
-                let value: *mut c_void = cif.call(code_ptr, args);
-                if value.is_null() {
-                    Ok(LuaValue::Nil)
-                } else {
-                    Ok(LuaValue::LightUserData(LuaLightUserData(value)))
+                let value: *mut c_void = timer.time(|| cif.call(code_ptr, args));
+                match signature.result_mode() {
+                    ResultMode::Integer => {
+                        let address = value as usize as u64;
+                        if address <= i64::MAX as u64 {
+                            Ok(LuaValue::Integer(address as i64))
+                        } else {
+                            Ok(LuaValue::Number(address as f64))
+                        }
+                    }
+                    ResultMode::Boolean => Ok(LuaValue::Boolean(!value.is_null())),
+                    _ if value.is_null() => Ok(LuaValue::Nil),
+                    ResultMode::String => {
+                        let text = CStr::from_ptr(value as *const c_char);
+                        Ok(LuaValue::String(lua.create_string(text.to_bytes())?))
+                    }
+                    _ => Ok(LuaValue::LightUserData(LuaLightUserData(value))),
+                }
+            }
+            TypeCode::LongDouble => Err(types::long_double_unsupported()),
+            TypeCode::Struct => {
+                // libffi writes at least a full register into the result buffer
+                let size = signature.result().size().max(mem::size_of::<usize>());
+                let buffer = libc::calloc(1, size);
+                if buffer.is_null() {
+                    return Err(error::range(format!(
+                        "failed to allocate {size} bytes for struct result"
+                    )));
                 }
+                call_record(timer, cif, code_ptr, args, buffer);
+                native::make_cdata(lua, result_descriptor.clone(), buffer, true)
+                    .inspect_err(|_| libc::free(buffer))
             }
         }
-    }
-}
+    }?;
 
-pub fn call(
-    _lua: &Lua,
-    func: LuaLightUserData,
-    signature_table: LuaTable,
-    args_table: LuaTable,
-) -> LuaResult<LuaValue> {
-    let signature = Signature::from_table(signature_table)?;
-    let (arg_values, arg_types, _owned_strings) = collect_arguments(args_table, &signature)?;
-    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
-    let cif = signature.build_cif(&arg_types);
-    call_with_signature(&signature, func, cif, &arg_refs)
+    // wide integers and pointers already honored the mode; this leaves the narrow integers
+    let value = match (signature.result_mode(), value) {
+        (ResultMode::Boolean, LuaValue::Integer(value)) => LuaValue::Boolean(value != 0),
+        (_, value) => value,
+    };
+    Ok((value, snapshot.errno))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::ffi::CStr;
-    use std::os::raw::{c_char, c_void};
+// 64-bit (and pointer-width) integer results under the signature's `resultMode`; `bits`
+// holds signed values sign-extended
+fn wide_result(lua: &Lua, signature: &Signature, bits: u64) -> LuaResult<LuaValue> {
+    let signed = matches!(
+        signature.result().code(),
+        TypeCode::Int64 | TypeCode::IntPtr
+    );
+    match signature.result_mode() {
+        ResultMode::Box if signed => native::box_integer(lua, "long long", bits),
+        ResultMode::Box => native::box_integer(lua, "unsigned long long", bits),
+        ResultMode::String if signed => Ok(LuaValue::String(
+            lua.create_string((bits as i64).to_string())?,
+        )),
+        ResultMode::String => Ok(LuaValue::String(lua.create_string(bits.to_string())?)),
+        ResultMode::Boolean => Ok(LuaValue::Boolean(bits != 0)),
+        _ if signed => Ok(LuaValue::Integer(bits as i64)),
+        _ => types::u64_to_lua(bits),
+    }
+}
 
-    struct RawBox<T>(*mut T);
+// small records come back in a register pair (e.g. rax:rdx or xmm0:xmm1 on SysV) and
+// larger ones through a hidden out-pointer; libffi classifies the record from its element
+// types and copies it into `out` either way, writing at least a full register
+unsafe fn call_record(
+    timer: &mut impl CallTimer,
+    cif: &Cif,
+    code_ptr: CodePtr,
+    args: &[Arg],
+    out: *mut c_void,
+) {
+    timer.time(|| unsafe {
+        libffi::raw::ffi_call(
+            cif.as_raw_ptr(),
+            Some(*code_ptr.as_fun()),
+            out,
+            args.as_ptr() as *mut *mut c_void,
+        )
+    });
+}
 
-    impl<T> RawBox<T> {
-        fn new(value: T) -> Self {
-            RawBox(Box::into_raw(Box::new(value)))
+// the storage a caller handed `call_into` for a struct result, after checking it can
+// hold `size` bytes; cdata whose type has no recorded size is trusted
+fn result_destination(value: &LuaValue, size: usize) -> LuaResult<*mut c_void> {
+    let (ptr, available) = match value {
+        LuaValue::Buffer(buffer) => (value.to_pointer() as *mut c_void, Some(buffer.len())),
+        LuaValue::Table(table) => {
+            let Some(info) = extract_cdata_info(table)? else {
+                return Err(error::type_error(
+                    "struct result destination must be cdata or a buffer",
+                ));
+            };
+            let ptr = info
+                .ptr
+                .filter(|ptr| !ptr.is_null())
+                .ok_or_else(|| error::null_pointer("struct result destination has no storage"))?;
+            let available = match table.raw_get::<Option<LuaTable>>("__ctype")? {
+                Some(ctype) => ctype.raw_get::<Option<usize>>("size")?,
+                None => None,
+            };
+            (ptr, available)
         }
-
-        fn ptr(&self) -> *mut T {
-            self.0
+        other => {
+            return Err(error::type_error(format!(
+                "struct result destination must be cdata or a buffer, got {}",
+                other.type_name()
+            )));
         }
+    };
+    if let Some(available) = available
+        && available < size
+    {
+        return Err(error::range(format!(
+            "destination of {available} byte(s) cannot hold a {size}-byte struct result"
+        )));
     }
+    Ok(ptr)
+}
 
-    impl<T> Drop for RawBox<T> {
-        fn drop(&mut self) {
-            if !self.0.is_null() {
-                unsafe {
-                    drop(Box::from_raw(self.0));
-                }
-                self.0 = std::ptr::null_mut();
-            }
-        }
+// a pointer result under `resultLength`: the length argument points at the byte count,
+// read after the call; a null length pointer leaves the raw pointer as it was
+fn sized_result(
+    lua: &Lua,
+    signature: &Signature,
+    arg_values: &[ArgValue],
+    value: LuaValue,
+) -> LuaResult<LuaValue> {
+    let Some(index) = signature.result_length() else {
+        return Ok(value);
+    };
+    let LuaValue::LightUserData(data) = value else {
+        return Ok(value);
+    };
+    let length_ptr = match arg_values.get(index) {
+        Some(ArgValue::Pointer(ptr)) if !ptr.is_null() => *ptr as *const usize,
+        _ => return Ok(value),
+    };
+    let len = unsafe { ptr::read_unaligned(length_ptr) };
+    if len > isize::MAX as usize {
+        return Err(error::range(format!(
+            "result length {len} is too large to read"
+        )));
     }
+    let bytes = unsafe { std::slice::from_raw_parts(data.0 as *const u8, len) };
+    Ok(LuaValue::String(lua.create_string(bytes)?))
+}
 
-    unsafe extern "C" {
-        fn luneffi_test_add_ints(a: i32, b: i32) -> i32;
-        fn luneffi_test_variadic_sum(count: i32, ...) -> i32;
-        fn luneffi_test_variadic_format(
-            buffer: *mut c_char,
-            size: usize,
-            fmt: *const c_char,
-            ...
-        ) -> i32;
+// `errno` is the value sampled as the foreign call returned
+fn check_result(signature: &Signature, value: LuaValue, errno: i32) -> LuaResult<LuaValue> {
+    let Some(check) = signature.on_error() else {
+        return Ok(value);
+    };
+    let errno = check.read_errno.then_some(errno);
+    if !check.matches(&value) {
+        return Ok(value);
     }
 
-    fn make_signature(
-        lua: &Lua,
-        result: &str,
-        args: &[&str],
-        variadic: bool,
-        fixed: usize,
-    ) -> LuaResult<LuaTable> {
-        let signature = lua.create_table()?;
-        signature.set("result", result)?;
-
-        let args_table = lua.create_table()?;
-        for (index, code) in args.iter().enumerate() {
-            args_table.set(index + 1, *code)?;
-        }
-        signature.set("args", args_table)?;
+    let shown = match &value {
+        LuaValue::Integer(n) => n.to_string(),
+        LuaValue::Number(n) => n.to_string(),
+        LuaValue::Nil => "NULL".to_string(),
+        LuaValue::LightUserData(ud) => format!("{:p}", ud.0),
+        other => other.type_name().to_string(),
+    };
+    let message = match errno {
+        Some(errno) => format!(
+            "call failed: result {shown} matched onError '{}' (errno {errno}: {})",
+            check.as_str(),
+            error::strerror(errno)
+        ),
+        None => format!(
+            "call failed: result {shown} matched onError '{}'",
+            check.as_str()
+        ),
+    };
+    let failure = error::call_failure(message, errno);
+    if !check.raise {
+        error::record(failure);
+        return Ok(value);
+    }
+    Err(failure.into())
+}
 
-        if variadic {
-            signature.set("variadic", true)?;
-            signature.set("fixedCount", fixed as u32)?;
-        }
+fn ensure_callable(func: LuaLightUserData) -> LuaResult<()> {
+    if func.0.is_null() {
+        return Err(error::null_pointer(
+            "attempt to call a null function pointer",
+        ));
+    }
+    Ok(())
+}
 
-        Ok(signature)
+// the segment lookup walks every loaded object, so it runs once when a function is bound
+// or prepared rather than on each call
+pub fn ensure_executable(func: LuaLightUserData) -> LuaResult<()> {
+    ensure_callable(func)?;
+    if native::is_callable(func.0) == Some(false) {
+        return Err(error::type_error(format!(
+            "attempt to call non-executable address {:p}",
+            func.0
+        )));
     }
+    Ok(())
+}
 
-    fn pack_args(lua: &Lua, values: Vec<LuaValue>) -> LuaResult<LuaTable> {
-        let len = values.len();
-        let args = lua.create_table()?;
-        for (index, value) in values.into_iter().enumerate() {
-            args.raw_set((index + 1) as i64, value)?;
+// `void f(void)` hooks are often called in loops; with nothing to marshal in either
+// direction every convention agrees, so they are called directly without a cif. The
+// signature table is checked as is, so these calls never build a `Signature` either:
+// anything beyond a void result, empty args, and options that only affect conversions
+// takes the full path.
+fn is_trivial(table: &LuaTable) -> LuaResult<bool> {
+    let mut seen_result = false;
+    let mut seen_args = false;
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        let LuaValue::String(key) = key else {
+            return Ok(false);
+        };
+        let trivial = match (&*key.as_bytes(), &value) {
+            (_, LuaValue::Nil | LuaValue::Boolean(false)) => true,
+            (b"kind", LuaValue::String(kind)) => &*kind.as_bytes() == b"function",
+            (b"wrap" | b"borrowStrings" | b"strictPointers", LuaValue::Boolean(_)) => true,
+            (b"result", LuaValue::String(code)) => {
+                seen_result = true;
+                &*code.as_bytes() == b"void"
+            }
+            (b"result", LuaValue::Table(ty)) => {
+                seen_result = true;
+                ty.raw_get::<Option<LuaString>>("code")?
+                    .is_some_and(|code| &*code.as_bytes() == b"void")
+            }
+            (b"args", LuaValue::Table(args)) => {
+                seen_args = true;
+                args.is_empty()
+            }
+            (b"fixedCount", LuaValue::Integer(0)) => true,
+            (b"fixedCount", LuaValue::Number(n)) => *n == 0.0,
+            (b"abi", LuaValue::String(abi)) => matches!(&*abi.as_bytes(), b"cdecl" | b"default"),
+            _ => false,
+        };
+        if !trivial {
+            return Ok(false);
         }
-        args.set("n", len)?;
-        Ok(args)
     }
+    Ok(seen_result && seen_args)
+}
 
-    fn make_cdata_table(lua: &Lua, code: &str, ptr: *mut c_void) -> LuaResult<LuaTable> {
-        let table = lua.create_table()?;
-        table.raw_set("__ffi_cdata", LuaValue::Boolean(true))?;
-        table.raw_set("__ptr", LuaValue::LightUserData(LuaLightUserData(ptr)))?;
+fn call_trivial(func: LuaLightUserData, arg_count: usize) -> LuaResult<LuaValue> {
+    if arg_count != 0 {
+        return Err(error::type_error(format!(
+            "function expected 0 argument(s) but received {arg_count}"
+        )));
+    }
 
-        let descriptor = lua.create_table()?;
-        descriptor.set("code", code)?;
-        descriptor.set("kind", "primitive")?;
-        table.raw_set("__ctype", LuaValue::Table(descriptor))?;
+    let entry: unsafe extern "C" fn() = unsafe { mem::transmute(func.0) };
+    unsafe { entry() };
+    Ok(LuaValue::Nil)
+}
 
-        Ok(table)
-    }
+// reaching the caller again means the declaration was wrong; nothing was read back
+fn noreturn_returned() -> LuaError {
+    error::call("function declared noreturn returned to its caller", None)
+}
 
-    #[test]
-    fn call_simple_add() -> LuaResult<()> {
-        let lua = Lua::new();
-        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
-        let args = pack_args(&lua, vec![LuaValue::Integer(12), LuaValue::Integer(30)])?;
-        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+pub fn call(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args_table: LuaTable,
+) -> LuaResult<LuaValue> {
+    ensure_callable(func)?;
+    if is_trivial(&signature_table)? {
+        let arg_count = match args_table.get::<Option<u32>>("n")? {
+            Some(n) => n as usize,
+            None => args_table.raw_len(),
+        };
+        return call_trivial(func, arg_count);
+    }
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    let (arg_values, arg_types, _owned_strings) = collect_arguments(args_table, &signature, true)?;
+    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+    let cif = signature.build_cif(&arg_types)?;
+    let (value, errno) = call_with_signature(
+        &mut Untimed,
+        lua,
+        &signature,
+        &result_descriptor,
+        func,
+        &cif,
+        &arg_refs,
+    )?;
+    let value = sized_result(lua, &signature, &arg_values, value)?;
+    check_result(&signature, value, errno)
+}
+
+// `call` with the arguments passed positionally, so fixed-arity callers skip packing a
+// table; trailing nils count, as with `select('#', ...)`, and variadic arguments are
+// inferred from their Lua values
+pub fn callv(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args: LuaMultiValue,
+) -> LuaResult<LuaValue> {
+    ensure_callable(func)?;
+    if is_trivial(&signature_table)? {
+        return call_trivial(func, args.len());
+    }
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    let (arg_values, arg_types, _owned_strings) =
+        convert_arguments(&signature, args.len(), None, true, |index| {
+            Ok(args[index].clone())
+        })?;
+    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+    let cif = signature.build_cif(&arg_types)?;
+    let (value, errno) = call_with_signature(
+        &mut Untimed,
+        lua,
+        &signature,
+        &result_descriptor,
+        func,
+        &cif,
+        &arg_refs,
+    )?;
+    let value = sized_result(lua, &signature, &arg_values, value)?;
+    check_result(&signature, value, errno)
+}
+
+// like `call` for struct-returning functions, but writes the record into `destination`
+// instead of allocating fresh cdata, and returns `destination`
+pub fn call_into(
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args_table: LuaTable,
+    destination: LuaValue,
+) -> LuaResult<LuaValue> {
+    ensure_callable(func)?;
+    let signature = Signature::from_table(signature_table)?;
+    if signature.result().code() != TypeCode::Struct {
+        return Err(error::type_error(format!(
+            "callInto needs a function returning a struct, not {}",
+            signature.result().code().name()
+        )));
+    }
+    let size = signature.result().size();
+    let out = result_destination(&destination, size)?;
+    let (arg_values, arg_types, _owned_strings) = collect_arguments(args_table, &signature, true)?;
+    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+    let cif = signature.build_cif(&arg_types)?;
+    let code_ptr = CodePtr::from_ptr(func.0 as *const c_void);
+
+    // libffi may store a whole register past the end of a record smaller than one
+    let word = mem::size_of::<usize>();
+    let mut timer = ErrnoSnapshot::new(&mut Untimed);
+    if size < word {
+        let mut scratch = [0usize; 1];
+        unsafe {
+            call_record(
+                &mut timer,
+                &cif,
+                code_ptr,
+                &arg_refs,
+                scratch.as_mut_ptr() as *mut c_void,
+            );
+            ptr::copy_nonoverlapping(scratch.as_ptr() as *const u8, out as *mut u8, size);
+        }
+    } else {
+        unsafe { call_record(&mut timer, &cif, code_ptr, &arg_refs, out) };
+    }
+    check_result(&signature, destination, timer.errno)
+}
+
+// like `call`, but hands recoverable failures back as a value for branch-on-failure loops
+pub fn try_call(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args_table: LuaTable,
+) -> LuaResult<(bool, LuaValue)> {
+    match call(lua, func, signature_table, args_table) {
+        Ok(value) => Ok((true, value)),
+        Err(err) if error::is_recoverable(&err) => Ok((false, LuaValue::Error(Box::new(err)))),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn call_timed(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args_table: LuaTable,
+) -> LuaResult<(LuaValue, u64)> {
+    ensure_callable(func)?;
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    let (arg_values, arg_types, _owned_strings) = collect_arguments(args_table, &signature, true)?;
+    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+    let cif = signature.build_cif(&arg_types)?;
+    let mut stopwatch = Stopwatch::default();
+    let (value, errno) = call_with_signature(
+        &mut stopwatch,
+        lua,
+        &signature,
+        &result_descriptor,
+        func,
+        &cif,
+        &arg_refs,
+    )?;
+    let value = sized_result(lua, &signature, &arg_values, value)?;
+    let value = check_result(&signature, value, errno)?;
+    let nanos = u64::try_from(stopwatch.elapsed.as_nanos()).unwrap_or(u64::MAX);
+    Ok((value, nanos))
+}
+
+pub fn call_with_errno_reset(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    args_table: LuaTable,
+) -> LuaResult<(LuaValue, i32)> {
+    ensure_callable(func)?;
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    let (arg_values, arg_types, _owned_strings) = collect_arguments(args_table, &signature, true)?;
+    let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+    let cif = signature.build_cif(&arg_types)?;
+    let mut reset = ErrnoReset::default();
+    let (value, errno) = call_with_signature(
+        &mut reset,
+        lua,
+        &signature,
+        &result_descriptor,
+        func,
+        &cif,
+        &arg_refs,
+    )?;
+    let value = sized_result(lua, &signature, &arg_values, value)?;
+    let value = check_result(&signature, value, errno)?;
+    Ok((value, reset.errno))
+}
+
+pub fn call_many(
+    lua: &Lua,
+    func: LuaLightUserData,
+    signature_table: LuaTable,
+    arg_sets: LuaTable,
+) -> LuaResult<LuaTable> {
+    ensure_callable(func)?;
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    if signature.is_variadic() {
+        return Err(error::type_error(
+            "callMany does not support variadic signatures",
+        ));
+    }
+
+    let cif = signature.build_cif(&signature.arg_types())?;
+    let count = arg_sets.raw_len();
+    let results = lua.create_table_with_capacity(count, 1)?;
+
+    for (index, args_table) in arg_sets.sequence_values::<LuaTable>().enumerate() {
+        let (arg_values, _, _owned_strings) = collect_arguments(args_table?, &signature, false)
+            .map_err(|err| err.context(format!("argument set {}", index + 1)))?;
+        let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+        let (value, errno) = call_with_signature(
+            &mut Untimed,
+            lua,
+            &signature,
+            &result_descriptor,
+            func,
+            &cif,
+            &arg_refs,
+        )?;
+        let value = sized_result(lua, &signature, &arg_values, value)?;
+        let value = check_result(&signature, value, errno)
+            .map_err(|err| err.context(format!("argument set {}", index + 1)))?;
+        results.raw_set(index + 1, value)?;
+    }
+
+    results.set("n", count)?;
+    Ok(results)
+}
+
+// a function pointer bound to a fixed signature with its cif built once, so each call
+// only converts its arguments
+pub struct PreparedCall {
+    func: LuaLightUserData,
+    signature: Signature,
+    result_descriptor: LuaValue,
+    cif: Cif,
+}
+
+impl PreparedCall {
+    fn invoke(&self, lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
+        let (arg_values, _, _owned_strings) =
+            convert_arguments(&self.signature, args.len(), None, false, |index| {
+                Ok(args[index].clone())
+            })?;
+        let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+        let (value, errno) = call_with_signature(
+            &mut Untimed,
+            lua,
+            &self.signature,
+            &self.result_descriptor,
+            self.func,
+            &self.cif,
+            &arg_refs,
+        )?;
+        let value = sized_result(lua, &self.signature, &arg_values, value)?;
+        check_result(&self.signature, value, errno)
+    }
+}
+
+impl LuaUserData for PreparedCall {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::Call, |lua, this, args: LuaMultiValue| {
+            this.invoke(lua, args)
+        });
+    }
+}
+
+pub fn prepare(func: LuaLightUserData, signature_table: LuaTable) -> LuaResult<PreparedCall> {
+    ensure_executable(func)?;
+    let result_descriptor: LuaValue = signature_table.get("result")?;
+    let signature = Signature::from_table(signature_table)?;
+    if signature.is_variadic() {
+        return Err(error::type_error(
+            "prepared calls do not support variadic signatures",
+        ));
+    }
+    let cif = signature.build_cif(&signature.arg_types())?;
+    Ok(PreparedCall {
+        func,
+        signature,
+        result_descriptor,
+        cif,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{AbiChoice, SignatureBuilder};
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_void};
+
+    struct RawBox<T>(*mut T);
+
+    impl<T> RawBox<T> {
+        fn new(value: T) -> Self {
+            RawBox(Box::into_raw(Box::new(value)))
+        }
+
+        fn ptr(&self) -> *mut T {
+            self.0
+        }
+    }
+
+    impl<T> Drop for RawBox<T> {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                unsafe {
+                    drop(Box::from_raw(self.0));
+                }
+                self.0 = std::ptr::null_mut();
+            }
+        }
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct IntPair {
+        a: i32,
+        b: i32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct DoublePair {
+        x: f64,
+        y: f64,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct LongTriple {
+        a: i64,
+        b: i64,
+        c: i64,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct IntVec3 {
+        values: [i32; 3],
+    }
+
+    #[repr(C, packed)]
+    #[allow(dead_code)]
+    struct PackedFloatPair {
+        x: f32,
+        y: f32,
+    }
+
+    #[repr(C, packed)]
+    #[allow(dead_code)]
+    struct PackedTagged {
+        tag: i8,
+        value: i32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    union IntFloat {
+        as_int: i32,
+        as_float: f32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    union DoubleSpan {
+        whole: f64,
+        halves: [f64; 2],
+    }
+
+    unsafe extern "C" {
+        fn luneffi_test_add_ints(a: i32, b: i32) -> i32;
+        fn luneffi_test_identity_pointer(value: *mut c_void) -> *mut c_void;
+        fn luneffi_test_counter_add(counter: *mut i32, delta: i32) -> i32;
+        fn luneffi_test_make_int_pair(a: i32, b: i32) -> IntPair;
+        fn luneffi_test_make_double_pair(x: f64, y: f64) -> DoublePair;
+        fn luneffi_test_make_long_triple(a: i64, b: i64, c: i64) -> LongTriple;
+        fn luneffi_test_make_int_vec3(a: i32, b: i32, c: i32) -> IntVec3;
+        fn luneffi_test_make_int_float(value: i32) -> IntFloat;
+        fn luneffi_test_make_packed_float_pair(x: f32, y: f32) -> PackedFloatPair;
+        fn luneffi_test_make_packed_tagged(tag: i8, value: i32) -> PackedTagged;
+        fn luneffi_test_make_double_span(first: f64, second: f64) -> DoubleSpan;
+        fn luneffi_test_variadic_sum(count: i32, ...) -> i32;
+        fn luneffi_test_variadic_none() -> i32;
+        fn luneffi_test_fail_with_errno(code: i32) -> i32;
+        fn luneffi_test_tick();
+        fn luneffi_test_sized_data(out_len: *mut usize) -> *const c_char;
+        fn luneffi_test_ticks() -> i32;
+        fn luneffi_test_variadic_format(
+            buffer: *mut c_char,
+            size: usize,
+            fmt: *const c_char,
+            ...
+        ) -> i32;
+    }
+
+    fn make_signature(
+        lua: &Lua,
+        result: &str,
+        args: &[&str],
+        variadic: bool,
+        fixed: usize,
+    ) -> LuaResult<LuaTable> {
+        let signature = lua.create_table()?;
+        signature.set("result", result)?;
+
+        let args_table = lua.create_table()?;
+        for (index, code) in args.iter().enumerate() {
+            args_table.set(index + 1, *code)?;
+        }
+        signature.set("args", args_table)?;
+
+        if variadic {
+            signature.set("variadic", true)?;
+            signature.set("fixedCount", fixed as u32)?;
+        }
+
+        Ok(signature)
+    }
+
+    fn pack_args(lua: &Lua, values: Vec<LuaValue>) -> LuaResult<LuaTable> {
+        let len = values.len();
+        let args = lua.create_table()?;
+        for (index, value) in values.into_iter().enumerate() {
+            args.raw_set((index + 1) as i64, value)?;
+        }
+        args.set("n", len)?;
+        Ok(args)
+    }
+
+    fn make_cdata_table(lua: &Lua, code: &str, ptr: *mut c_void) -> LuaResult<LuaTable> {
+        let table = lua.create_table()?;
+        table.raw_set("__ffi_cdata", LuaValue::Boolean(true))?;
+        table.raw_set("__ptr", LuaValue::LightUserData(LuaLightUserData(ptr)))?;
+
+        let descriptor = lua.create_table()?;
+        descriptor.set("code", code)?;
+        descriptor.set("kind", "primitive")?;
+        table.raw_set("__ctype", LuaValue::Table(descriptor))?;
+
+        Ok(table)
+    }
+
+    #[test]
+    fn call_records_errors_instead_of_raising_when_asked() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32"], false, 1)?;
+        let check = lua.create_table()?;
+        check.set("when", "minusOne")?;
+        check.set("read", "errno")?;
+        check.set("raise", false)?;
+        signature.set("onError", check)?;
+        let func = LuaLightUserData(luneffi_test_fail_with_errno as *const () as *mut c_void);
+
+        error::clear();
+        let result = call(
+            &lua,
+            func,
+            signature.clone(),
+            pack_args(&lua, vec![LuaValue::Integer(0)])?,
+        )?;
+        assert!(matches!(result, LuaValue::Integer(0)));
+        assert!(error::last().is_none());
+
+        let result = call(
+            &lua,
+            func,
+            signature,
+            pack_args(&lua, vec![LuaValue::Integer(libc::EINVAL as i64)])?,
+        )?;
+        assert!(matches!(result, LuaValue::Integer(-1)));
+        let recorded = error::last().expect("the failure should be recorded");
+        assert_eq!(recorded.kind(), error::ErrorKind::Call);
+        assert_eq!(recorded.errno(), Some(libc::EINVAL));
+
+        error::clear();
+        assert!(error::last().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn try_call_returns_recoverable_failures_as_values() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+
+        let (ok, value) = try_call(
+            &lua,
+            func,
+            signature.clone(),
+            pack_args(&lua, vec![LuaValue::Integer(40), LuaValue::Integer(2)])?,
+        )?;
+        assert!(ok);
+        assert!(matches!(value, LuaValue::Integer(42)));
+
+        let (ok, value) = try_call(
+            &lua,
+            func,
+            signature.clone(),
+            pack_args(&lua, vec![LuaValue::Integer(1 << 40), LuaValue::Integer(2)])?,
+        )?;
+        assert!(!ok);
+        let LuaValue::Error(err) = value else {
+            panic!("expected an error value, got {value:?}");
+        };
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Range)
+        );
+
+        let (ok, value) = try_call(
+            &lua,
+            LuaLightUserData(ptr::null_mut()),
+            signature,
+            pack_args(&lua, vec![LuaValue::Integer(1), LuaValue::Integer(2)])?,
+        )?;
+        assert!(!ok);
+        assert!(matches!(value, LuaValue::Error(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn call_with_errno_reset_reports_only_the_calls_errno() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "long", &["pointer", "pointer", "int32"], false, 3)?;
+        let func = LuaLightUserData(libc::strtol as *const () as *mut c_void);
+        let parse = |text: &str| -> LuaResult<(LuaValue, i32)> {
+            let args = pack_args(
+                &lua,
+                vec![
+                    LuaValue::String(lua.create_string(text)?),
+                    LuaValue::Nil,
+                    LuaValue::Integer(10),
+                ],
+            )?;
+            call_with_errno_reset(&lua, func, signature.clone(), args)
+        };
+
+        let (value, errno) = parse("99999999999999999999999")?;
+        assert!(matches!(value, LuaValue::Integer(max) if max == libc::c_long::MAX as i64));
+        assert_eq!(errno, libc::ERANGE);
+
+        // a stale errno from earlier work must not leak into the snapshot
+        native::set_errno(libc::EINVAL);
+        let (value, errno) = parse("0")?;
+        assert!(matches!(value, LuaValue::Integer(0)));
+        assert_eq!(errno, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn call_void_functions_without_arguments_directly() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "void", &[], false, 0)?;
+        let func = LuaLightUserData(luneffi_test_tick as *const () as *mut c_void);
+
+        let before = unsafe { luneffi_test_ticks() };
+        for _ in 0..3 {
+            let result = call(&lua, func, signature.clone(), pack_args(&lua, vec![])?)?;
+            assert!(matches!(result, LuaValue::Nil));
+        }
+        assert_eq!(unsafe { luneffi_test_ticks() }, before + 3);
+
+        let err = call(
+            &lua,
+            func,
+            signature,
+            pack_args(&lua, vec![LuaValue::Integer(1)])?,
+        )
+        .expect_err("extra arguments should still be rejected");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Type)
+        );
+        assert_eq!(unsafe { luneffi_test_ticks() }, before + 3);
+        Ok(())
+    }
+
+    #[test]
+    fn only_plain_flags_keep_the_direct_path() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "void", &[], false, 0)?;
+        signature.set("kind", "function")?;
+        signature.set("wrap", true)?;
+        signature.set("strictPointers", false)?;
+        assert!(is_trivial(&signature)?);
+
+        for (key, value) in [
+            ("kind", LuaValue::String(lua.create_string("callback")?)),
+            ("wrap", LuaValue::String(lua.create_string("yes")?)),
+            ("borrowStrings", LuaValue::Integer(1)),
+            ("strictPointers", LuaValue::Table(lua.create_table()?)),
+        ] {
+            let signature = make_signature(&lua, "void", &[], false, 0)?;
+            signature.set(key, value)?;
+            assert!(!is_trivial(&signature)?, "{key} should need full parsing");
+        }
+        Ok(())
+    }
+
+    // cargo test -p lune-std-ffi -- --ignored --nocapture trivial_call_overhead
+    #[test]
+    #[ignore = "prints timings instead of asserting"]
+    fn trivial_call_overhead() -> LuaResult<()> {
+        const CALLS: u32 = 1_000_000;
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_tick as *const () as *mut c_void);
+        let args = pack_args(&lua, vec![])?;
+        let direct = make_signature(&lua, "void", &[], false, 0)?;
+        // an option the fast path does not recognize sends the same call through the cif
+        let marshaled = make_signature(&lua, "void", &[], false, 0)?;
+        marshaled.set("resultOptions", lua.create_table()?)?;
+
+        for (label, signature) in [("direct", direct), ("marshaled", marshaled)] {
+            let start = Instant::now();
+            for _ in 0..CALLS {
+                call(&lua, func, signature.clone(), args.clone())?;
+            }
+            println!("{label}: {:?} per call", start.elapsed() / CALLS);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn signature_rejects_untyped_or_void_fixed_arguments() -> LuaResult<()> {
+        let lua = Lua::new();
+        let expect_error = |signature: LuaTable, needle: &str| {
+            let err = Signature::from_table(signature).expect_err("signature should be rejected");
+            let ffi_err = error::find(&err).expect("expected structured ffi error");
+            assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+            assert!(
+                ffi_err.message().contains(needle),
+                "unexpected message: {}",
+                ffi_err.message()
+            );
+        };
+
+        // a hole in the fixed prefix of a variadic signature
+        let signature = make_signature(&lua, "int32", &["int32"], true, 3)?;
+        signature.get::<LuaTable>("args")?.raw_set(3, "double")?;
+        expect_error(signature, "argument 2 has no type");
+
+        let signature = make_signature(&lua, "int32", &["int32", "void"], true, 2)?;
+        expect_error(signature, "argument 2 cannot be void");
+
+        let signature = make_signature(&lua, "int32", &["int32"], false, 1)?;
+        signature.get::<LuaTable>("args")?.raw_set(3, "int32")?;
+        expect_error(signature, "argument 2 has no type");
+        Ok(())
+    }
+
+    #[test]
+    fn call_simple_add() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(12), LuaValue::Integer(30)])?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let result = call(&lua, func, signature, args)?;
+        match result {
+            LuaValue::Integer(value) => assert_eq!(value, 42),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn callv_takes_positional_arguments() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let args = LuaMultiValue::from_vec(vec![LuaValue::Integer(12), LuaValue::Integer(30)]);
+        let result = callv(&lua, func, signature.clone(), args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+
+        // a trailing nil is still an argument
+        let args = LuaMultiValue::from_vec(vec![
+            LuaValue::Integer(12),
+            LuaValue::Integer(30),
+            LuaValue::Nil,
+        ]);
+        let err = callv(&lua, func, signature, args).expect_err("three arguments for two");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Type)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_signatures_raise_type_errors() {
+        let err = Signature::from_parts(
+            AbiChoice::Default,
+            CType::primitive(TypeCode::Int32),
+            vec![CType::primitive(TypeCode::Int32)],
+            false,
+            Some(0),
+        )
+        .expect_err("fixedCount must cover every argument");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+
+        for name in ["no_such_abi", "regparm"] {
+            let err = AbiChoice::from_option(Some(name.to_string()))
+                .expect_err("ABIs libffi cannot describe are rejected");
+            let ffi_err = error::find(&err).expect("expected structured ffi error");
+            assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        }
+    }
+
+    #[test]
+    fn call_with_prebuilt_signature() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = Signature::from_parts(
+            AbiChoice::Default,
+            CType::primitive(TypeCode::Int32),
+            vec![
+                CType::primitive(TypeCode::Int32),
+                CType::primitive(TypeCode::Int32),
+            ],
+            false,
+            None,
+        )?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(5), LuaValue::Integer(6)])?;
+        let (arg_values, arg_types, _owned) = collect_arguments(args, &signature, true)?;
+        let arg_refs: Vec<Arg> = arg_values.iter().map(ArgValue::as_arg).collect();
+        let cif = signature.build_cif(&arg_types)?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let (result, _) = call_with_signature(
+            &mut Untimed,
+            &lua,
+            &signature,
+            &LuaValue::Nil,
+            func,
+            &cif,
+            &arg_refs,
+        )?;
+        assert!(matches!(result, LuaValue::Integer(11)));
+        Ok(())
+    }
+
+    fn make_struct_descriptor(lua: &Lua, codes: &[&str]) -> LuaResult<LuaTable> {
+        let fields = lua.create_table()?;
+        for (index, code) in codes.iter().enumerate() {
+            let field = lua.create_table()?;
+            field.set("ctype", *code)?;
+            fields.set(index + 1, field)?;
+        }
+        let descriptor = lua.create_table()?;
+        descriptor.set("kind", "struct")?;
+        descriptor.set("code", "struct")?;
+        descriptor.set("fields", fields)?;
+        Ok(descriptor)
+    }
+
+    fn call_struct<T: Copy>(
+        lua: &Lua,
+        func: *const (),
+        fields: &[&str],
+        arg_codes: &[&str],
+        args: Vec<LuaValue>,
+    ) -> LuaResult<Vec<T>> {
+        let signature = make_signature(lua, "void", arg_codes, false, arg_codes.len())?;
+        signature.set("result", make_struct_descriptor(lua, fields)?)?;
+        let args = pack_args(lua, args)?;
+        let result = call(lua, LuaLightUserData(func as *mut c_void), signature, args)?;
+        let LuaValue::Table(cdata) = result else {
+            panic!("expected cdata table, got {result:?}");
+        };
+        assert!(cdata.raw_get::<bool>("__owned")?);
+        let ptr = cdata.raw_get::<LuaLightUserData>("__ptr")?.0 as *mut T;
+        let values = (0..fields.len())
+            .map(|index| unsafe { *ptr.add(index) })
+            .collect();
+        unsafe { libc::free(ptr as *mut c_void) };
+        Ok(values)
+    }
+
+    #[test]
+    fn call_returns_small_structs_in_registers() -> LuaResult<()> {
+        let lua = Lua::new();
+        let ints: Vec<i32> = call_struct(
+            &lua,
+            luneffi_test_make_int_pair as *const (),
+            &["int32", "int32"],
+            &["int32", "int32"],
+            vec![LuaValue::Integer(7), LuaValue::Integer(-9)],
+        )?;
+        assert_eq!(ints, vec![7, -9]);
+
+        let doubles: Vec<f64> = call_struct(
+            &lua,
+            luneffi_test_make_double_pair as *const (),
+            &["double", "double"],
+            &["double", "double"],
+            vec![LuaValue::Number(1.5), LuaValue::Number(-2.25)],
+        )?;
+        assert_eq!(doubles, vec![1.5, -2.25]);
+        Ok(())
+    }
+
+    fn call_aggregate(
+        lua: &Lua,
+        func: *const (),
+        result: LuaTable,
+        arg_codes: &[&str],
+        args: Vec<LuaValue>,
+    ) -> LuaResult<*mut c_void> {
+        let signature = make_signature(lua, "void", arg_codes, false, arg_codes.len())?;
+        signature.set("result", result)?;
+        let args = pack_args(lua, args)?;
+        let result = call(lua, LuaLightUserData(func as *mut c_void), signature, args)?;
+        let LuaValue::Table(cdata) = result else {
+            panic!("expected cdata table, got {result:?}");
+        };
+        assert!(cdata.raw_get::<bool>("__owned")?);
+        Ok(cdata.raw_get::<LuaLightUserData>("__ptr")?.0)
+    }
+
+    fn make_array_descriptor(lua: &Lua, base: &str, count: usize) -> LuaResult<LuaTable> {
+        let descriptor = lua.create_table()?;
+        descriptor.set("kind", "array")?;
+        descriptor.set("code", "array")?;
+        descriptor.set("base", base)?;
+        descriptor.set("count", count)?;
+        Ok(descriptor)
+    }
+
+    #[test]
+    fn call_returns_records_holding_arrays() -> LuaResult<()> {
+        let lua = Lua::new();
+        let field = lua.create_table()?;
+        field.set("ctype", make_array_descriptor(&lua, "int32", 3)?)?;
+        let result = make_struct_descriptor(&lua, &[])?;
+        result.set("fields", lua.create_sequence_from([field])?)?;
+
+        let ptr = call_aggregate(
+            &lua,
+            luneffi_test_make_int_vec3 as *const (),
+            result,
+            &["int32", "int32", "int32"],
+            vec![
+                LuaValue::Integer(4),
+                LuaValue::Integer(-5),
+                LuaValue::Integer(6),
+            ],
+        )?;
+        let values = unsafe { (*(ptr as *const IntVec3)).values };
+        unsafe { libc::free(ptr) };
+        assert_eq!(values, [4, -5, 6]);
+
+        let array = make_array_descriptor(&lua, "int32", 3)?;
+        let ty = CType::from_lua(LuaValue::Table(array))?;
+        assert_eq!(ty.size(), mem::size_of::<IntVec3>());
+        Ok(())
+    }
+
+    #[test]
+    fn call_returns_unions_by_value() -> LuaResult<()> {
+        let lua = Lua::new();
+        let result = make_struct_descriptor(&lua, &["int32", "float"])?;
+        result.set("kind", "union")?;
+        result.set("code", "union")?;
+        let ptr = call_aggregate(
+            &lua,
+            luneffi_test_make_int_float as *const (),
+            result,
+            &["int32"],
+            vec![LuaValue::Integer(0x3f80_0000)],
+        )?;
+        let value = unsafe { (*(ptr as *const IntFloat)).as_float };
+        unsafe { libc::free(ptr) };
+        assert_eq!(value, 1.0);
+
+        // a union of doubles comes back in floating registers
+        let halves = lua.create_table()?;
+        halves.set("ctype", make_array_descriptor(&lua, "double", 2)?)?;
+        let result = make_struct_descriptor(&lua, &["double"])?;
+        result.set("kind", "union")?;
+        result.set("code", "union")?;
+        result.get::<LuaTable>("fields")?.push(halves)?;
+        let ptr = call_aggregate(
+            &lua,
+            luneffi_test_make_double_span as *const (),
+            result,
+            &["double", "double"],
+            vec![LuaValue::Number(1.25), LuaValue::Number(-8.5)],
+        )?;
+        let halves = unsafe { (*(ptr as *const DoubleSpan)).halves };
+        unsafe { libc::free(ptr) };
+        assert_eq!(halves, [1.25, -8.5]);
+        Ok(())
+    }
+
+    #[test]
+    fn call_returns_packed_records_only_when_naturally_aligned() -> LuaResult<()> {
+        let lua = Lua::new();
+        let result = make_struct_descriptor(&lua, &["float", "float"])?;
+        result.set("packed", true)?;
+        let ptr = call_aggregate(
+            &lua,
+            luneffi_test_make_packed_float_pair as *const (),
+            result,
+            &["float", "float"],
+            vec![LuaValue::Number(1.5), LuaValue::Number(-4.0)],
+        )?;
+        let pair = unsafe { ptr::read_unaligned(ptr as *const PackedFloatPair) };
+        unsafe { libc::free(ptr) };
+        assert_eq!((pair.x, pair.y), (1.5, -4.0));
+
+        // `value` sits at offset 1, which libffi has no way to describe
+        let signature = make_signature(&lua, "void", &["int8", "int32"], false, 2)?;
+        let result = make_struct_descriptor(&lua, &["int8", "int32"])?;
+        result.set("packed", true)?;
+        signature.set("result", result)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(1), LuaValue::Integer(2)])?;
+        let func = LuaLightUserData(luneffi_test_make_packed_tagged as *const () as *mut c_void);
+        let err =
+            call(&lua, func, signature, args).expect_err("unaligned packed fields are rejected");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        Ok(())
+    }
+
+    #[test]
+    fn call_into_writes_struct_results_into_caller_storage() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "void", &["int32", "int32"], false, 2)?;
+        signature.set("result", make_struct_descriptor(&lua, &["int32", "int32"])?)?;
+        let func = LuaLightUserData(luneffi_test_make_int_pair as *const () as *mut c_void);
+
+        let buffer = lua.create_buffer(vec![0u8; 8])?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(3), LuaValue::Integer(-4)])?;
+        let returned = call_into(
+            func,
+            signature.clone(),
+            args,
+            LuaValue::Buffer(buffer.clone()),
+        )?;
+        assert!(matches!(returned, LuaValue::Buffer(_)));
+        assert_eq!(i32::from_ne_bytes(buffer.read_bytes::<4>(0)), 3);
+        assert_eq!(i32::from_ne_bytes(buffer.read_bytes::<4>(4)), -4);
+
+        let short = lua.create_buffer(vec![0u8; 4])?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(3), LuaValue::Integer(-4)])?;
+        let err = call_into(func, signature, args, LuaValue::Buffer(short))
+            .expect_err("a 4-byte buffer cannot hold an 8-byte struct");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Range)
+        );
+
+        // records narrower than a register go through scratch space, so an exact-size
+        // destination is enough
+        let signature = make_signature(&lua, "void", &["int32"], false, 1)?;
+        let result = make_struct_descriptor(&lua, &["int32", "float"])?;
+        result.set("kind", "union")?;
+        result.set("code", "union")?;
+        signature.set("result", result)?;
+        let exact = lua.create_buffer(vec![0u8; 4])?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(0x3f80_0000)])?;
+        let func = LuaLightUserData(luneffi_test_make_int_float as *const () as *mut c_void);
+        call_into(func, signature, args, LuaValue::Buffer(exact.clone()))?;
+        assert_eq!(f32::from_ne_bytes(exact.read_bytes::<4>(0)), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn call_returns_large_structs_through_memory() -> LuaResult<()> {
+        let lua = Lua::new();
+        let longs: Vec<i64> = call_struct(
+            &lua,
+            luneffi_test_make_long_triple as *const (),
+            &["int64", "int64", "int64"],
+            &["int64", "int64", "int64"],
+            vec![
+                LuaValue::Integer(1),
+                LuaValue::Integer(i64::MAX),
+                LuaValue::Integer(-3),
+            ],
+        )?;
+        assert_eq!(longs, vec![1, i64::MAX, -3]);
+        Ok(())
+    }
+
+    #[test]
+    fn call_returns_pointer_results_as_integers() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "pointer", &["pointer"], false, 1)?;
+        let options = lua.create_table()?;
+        options.set("asInteger", true)?;
+        signature.set("resultOptions", options)?;
+
+        let func = LuaLightUserData(luneffi_test_identity_pointer as *const () as *mut c_void);
+        let value = RawBox::new(0u8);
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::LightUserData(LuaLightUserData(
+                value.ptr() as *mut c_void
+            ))],
+        )?;
+        let result = call(&lua, func, signature.clone(), args)?;
+        assert!(matches!(result, LuaValue::Integer(address) if address == value.ptr() as i64));
+
+        let args = pack_args(&lua, vec![LuaValue::Nil])?;
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(0)));
+        Ok(())
+    }
+
+    #[test]
+    fn call_with_signature_parsed_from_c_declaration() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = crate::cdecl::parse(&lua, "int luneffi_test_add_ints(int a, int b);")?;
+        assert_eq!(signature.get::<String>("name")?, "luneffi_test_add_ints");
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let args = pack_args(&lua, vec![LuaValue::Integer(19), LuaValue::Integer(23)])?;
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+
+        let signature = crate::cdecl::parse(&lua, "int luneffi_test_variadic_sum(int count, ...)")?;
+        assert!(signature.get::<bool>("variadic")?);
+        assert_eq!(signature.get::<usize>("fixedCount")?, 1);
+        let func = LuaLightUserData(luneffi_test_variadic_sum as *const () as *mut c_void);
+        let args = pack_args(
+            &lua,
+            vec![
+                LuaValue::Integer(2),
+                LuaValue::Integer(5),
+                LuaValue::Integer(6),
+            ],
+        )?;
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(11)));
+
+        let err = crate::cdecl::parse(&lua, "int broken(int, float x y)").unwrap_err();
+        assert!(err.to_string().contains("column 25"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn call_borrows_nul_safe_strings_when_requested() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "pointer", &["pointer"], false, 1)?;
+        let options = lua.create_table()?;
+        options.set("asInteger", true)?;
+        signature.set("resultOptions", options)?;
+        let func = LuaLightUserData(luneffi_test_identity_pointer as *const () as *mut c_void);
+
+        let text = lua.create_string("borrowed bytes")?;
+        let address = text.as_bytes().as_ptr() as i64;
+        let args = pack_args(&lua, vec![LuaValue::String(text.clone())])?;
+        let result = call(&lua, func, signature.clone(), args)?;
+        assert!(matches!(result, LuaValue::Integer(copied) if copied != address));
+
+        signature.set("borrowStrings", true)?;
+        let args = pack_args(&lua, vec![LuaValue::String(text)])?;
+        let result = call(&lua, func, signature.clone(), args)?;
+        assert!(matches!(result, LuaValue::Integer(borrowed) if borrowed == address));
+
+        let args = pack_args(&lua, vec![LuaValue::String(lua.create_string(b"a\0b")?)])?;
+        let err = call(&lua, func, signature, args).unwrap_err();
+        assert!(err.to_string().contains("NUL byte"));
+        Ok(())
+    }
+
+    #[test]
+    fn call_wraps_out_of_range_arguments_when_requested() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        signature.set("wrap", true)?;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Integer((1 << 32) + 5), LuaValue::Integer(1)],
+        )?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(6)));
+        Ok(())
+    }
+
+    #[test]
+    fn call_rejects_null_and_data_pointers() -> LuaResult<()> {
+        static DATA: i32 = 7;
+        let lua = Lua::new();
+
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(1), LuaValue::Integer(2)])?;
+        let err = call(&lua, LuaLightUserData(ptr::null_mut()), signature, args)
+            .expect_err("null function pointer should be rejected");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::NullPointer);
+
+        if cfg!(any(target_os = "linux", target_os = "windows")) {
+            let data = &DATA as *const i32 as *mut c_void;
+            assert_eq!(native::is_callable(data), Some(false));
+            let code = luneffi_test_add_ints as *const () as *mut c_void;
+            assert_eq!(native::is_callable(code), Some(true));
+
+            let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+            let err = prepare(LuaLightUserData(data), signature)
+                .err()
+                .expect("data pointers cannot be prepared");
+            let ffi_err = error::find(&err).expect("expected structured ffi error");
+            assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn call_loads_scalar_cdata_for_typed_arguments() -> LuaResult<()> {
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let boxed_int = RawBox::new(40i16);
+        let boxed_double = RawBox::new(1.5f64);
+
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let int_cdata = make_cdata_table(&lua, "int16", boxed_int.ptr() as *mut c_void)?;
+        let args = pack_args(&lua, vec![LuaValue::Table(int_cdata), LuaValue::Integer(2)])?;
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let double_cdata = make_cdata_table(&lua, "double", boxed_double.ptr() as *mut c_void)?;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Table(double_cdata), LuaValue::Integer(2)],
+        )?;
+        let err = call(&lua, func, signature, args).expect_err("double cdata is not an int");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        Ok(())
+    }
+
+    #[test]
+    fn call_reinterprets_opaque_cdata_of_matching_size() -> LuaResult<()> {
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let boxed = RawBox::new(IntPair { a: 40, b: 7 });
+
+        let opaque = |size: usize| -> LuaResult<LuaTable> {
+            let table = make_cdata_table(&lua, "struct", boxed.ptr() as *mut c_void)?;
+            let descriptor = lua.create_table()?;
+            descriptor.set("kind", "struct")?;
+            descriptor.set("size", size)?;
+            table.raw_set("__ctype", descriptor)?;
+            Ok(table)
+        };
+
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Table(opaque(4)?), LuaValue::Integer(2)],
+        )?;
         let result = call(&lua, func, signature, args)?;
-        match result {
-            LuaValue::Integer(value) => assert_eq!(value, 42),
+        assert!(matches!(result, LuaValue::Integer(42)));
+
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Table(opaque(8)?), LuaValue::Integer(2)],
+        )?;
+        let err = call(&lua, func, signature, args).expect_err("size mismatch must be refused");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Range);
+        Ok(())
+    }
+
+    #[test]
+    fn result_length_reads_pointer_results_as_sized_strings() -> LuaResult<()> {
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_sized_data as *const () as *mut c_void);
+        let signature = make_signature(&lua, "pointer", &["pointer"], false, 1)?;
+        signature.set("resultLength", 1)?;
+
+        let out_len = lua.create_buffer(vec![0u8; mem::size_of::<usize>()])?;
+        let args = pack_args(&lua, vec![LuaValue::Buffer(out_len)])?;
+        let result = call(&lua, func, signature.clone(), args)?;
+        let LuaValue::String(data) = result else {
+            panic!("expected a string result, got {result:?}");
+        };
+        assert_eq!(&*data.as_bytes(), b"ab\0cd");
+
+        // without somewhere to write the length there is nothing to size the read by
+        let args = pack_args(&lua, vec![LuaValue::Nil])?;
+        let result = call(&lua, func, signature.clone(), args)?;
+        assert!(matches!(result, LuaValue::LightUserData(_)));
+
+        signature.set("resultLength", 2)?;
+        let err = Signature::from_table(signature).expect_err("there is no second argument");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+
+        let signature = make_signature(&lua, "pointer", &["int32"], false, 1)?;
+        signature.set("resultLength", 1)?;
+        let err = Signature::from_table(signature).expect_err("the length needs a pointer");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn noreturn_calls_end_the_process() -> LuaResult<()> {
+        const NORETURN_CHILD: &str = "LUNE_FFI_NORETURN_CHILD";
+        let lua = Lua::new();
+        let signature = crate::cdecl::parse(&lua, "_Noreturn void _exit(int status);")?;
+        assert!(signature.get::<bool>("noreturn")?);
+
+        // the call ends the process, so the test binary runs just this test again in a
+        // child; forking inside the threaded test harness could deadlock
+        if std::env::var_os(NORETURN_CHILD).is_some() {
+            let func = LuaLightUserData(libc::_exit as *const () as *mut c_void);
+            let args = pack_args(&lua, vec![LuaValue::Integer(7)])?;
+            let _ = call(&lua, func, signature, args);
+            unsafe { libc::_exit(99) };
+        }
+        let status = std::process::Command::new(std::env::current_exe().expect("test binary"))
+            .args([
+                "--exact",
+                "call::tests::noreturn_calls_end_the_process",
+                "--test-threads=1",
+            ])
+            .env(NORETURN_CHILD, "1")
+            .status()
+            .expect("failed to re-run the test binary");
+        assert_eq!(status.code(), Some(7));
+        Ok(())
+    }
+
+    #[test]
+    fn noreturn_calls_that_return_are_reported() -> LuaResult<()> {
+        let lua = Lua::new();
+        // a function that comes back anyway is reported rather than read from
+        let signature = make_signature(&lua, "void", &["pointer"], false, 1)?;
+        signature.set("noreturn", true)?;
+        let func = LuaLightUserData(libc::free as *const () as *mut c_void);
+        let args = pack_args(&lua, vec![LuaValue::Nil])?;
+        let err = call(&lua, func, signature, args).expect_err("free returns");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Call);
+
+        let signature = make_signature(&lua, "int32", &[], false, 0)?;
+        signature.set("noreturn", true)?;
+        let err = Signature::from_table(signature).expect_err("noreturn needs a void result");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        Ok(())
+    }
+
+    #[test]
+    fn call_prepends_this_for_member_functions() -> LuaResult<()> {
+        let lua = Lua::new();
+        let counter = RawBox::new(10i32);
+        let signature = make_signature(&lua, "int32", &["int32"], false, 1)?;
+        signature.set("memberFunction", true)?;
+        if cfg!(any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "aarch64"
+        )) {
+            signature.set("abi", "thiscall")?;
+        }
+
+        let args = pack_args(
+            &lua,
+            vec![
+                LuaValue::LightUserData(LuaLightUserData(counter.ptr() as *mut c_void)),
+                LuaValue::Integer(5),
+            ],
+        )?;
+        let func = LuaLightUserData(luneffi_test_counter_add as *const () as *mut c_void);
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(15)));
+        assert_eq!(unsafe { *counter.ptr() }, 15);
+        Ok(())
+    }
+
+    #[test]
+    fn member_functions_reject_other_abis() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32"], false, 1)?;
+        signature.set("memberFunction", true)?;
+        signature.set("abi", "sysv")?;
+        let err =
+            Signature::from_table(signature).expect_err("memberFunction needs a this-first ABI");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+
+        let signature = make_signature(&lua, "int32", &["int32"], true, 1)?;
+        signature.set("abi", "thiscall")?;
+        let err = Signature::from_table(signature).expect_err("thiscall cannot be variadic");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        Ok(())
+    }
+
+    #[test]
+    fn strict_pointers_refuse_numeric_addresses() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "pointer", &["pointer"], false, 1)?;
+        let func = LuaLightUserData(luneffi_test_identity_pointer as *const () as *mut c_void);
+        let numeric = || pack_args(&lua, vec![LuaValue::Integer(42)]);
+
+        assert!(call(&lua, func, signature.clone(), numeric()?).is_ok());
+
+        signature.set("strictPointers", true)?;
+        let err = call(&lua, func, signature.clone(), numeric()?)
+            .expect_err("numbers should be refused as pointers");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        assert!(ffi_err.message().contains("argument 1"));
+
+        let mut value = 7i32;
+        let address = &mut value as *mut i32 as *mut c_void;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::LightUserData(LuaLightUserData(address))],
+        )?;
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::LightUserData(ptr) if ptr.0 == address));
+        Ok(())
+    }
+
+    #[test]
+    fn call_accepts_target_specific_abi_names() -> LuaResult<()> {
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let native_abi = if cfg!(all(target_arch = "x86_64", unix)) {
+            Some("sysv_abi")
+        } else if cfg!(all(target_arch = "x86_64", windows)) {
+            Some("win64")
+        } else {
+            None
+        };
+        if let Some(abi) = native_abi {
+            let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+            signature.set("abi", abi)?;
+            let args = pack_args(&lua, vec![LuaValue::Integer(40), LuaValue::Integer(2)])?;
+            let result = call(&lua, func, signature, args)?;
+            assert!(matches!(result, LuaValue::Integer(42)));
+        }
+
+        assert!(AbiChoice::from_option(Some("regparm".to_string())).is_err());
+        assert_eq!(
+            AbiChoice::from_option(Some("fastcall".to_string())).is_ok(),
+            cfg!(target_arch = "x86")
+        );
+        assert_eq!(
+            AbiChoice::from_option(Some("gnuw64".to_string())).is_ok(),
+            cfg!(target_arch = "x86_64")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_calls_reuse_their_cif() -> LuaResult<()> {
+        let lua = Lua::new();
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let prepared = lua.create_userdata(prepare(func, signature)?)?;
+
+        for value in 0..3 {
+            let result: i64 = prepared.call((40, value))?;
+            assert_eq!(result, 40 + value);
+        }
+        let err = prepared
+            .call::<LuaValue>(40)
+            .expect_err("the argument count is still checked");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Type)
+        );
+
+        let variadic = make_signature(&lua, "int32", &["pointer"], true, 1)?;
+        assert!(prepare(func, variadic).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cdata_pointers_include_their_offset() -> LuaResult<()> {
+        let lua = Lua::new();
+        let slots = RawBox::new([0i32; 3]);
+        let cdata = make_cdata_table(&lua, "int", slots.ptr() as *mut c_void)?;
+        cdata.raw_set("__offset", 8)?;
+
+        let expected = slots.ptr().wrapping_byte_add(8) as *mut c_void;
+        let info = extract_cdata_info(&cdata)?.expect("table is cdata");
+        assert_eq!(info.ptr, Some(expected));
+        let ptr = assert_cdata_type(&LuaValue::Table(cdata.clone()), "int")?;
+        assert_eq!(ptr, Some(expected));
+
+        cdata.raw_set("__offset", -8)?;
+        let err = extract_cdata_info(&cdata).expect_err("negative offsets are rejected");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Range);
+        Ok(())
+    }
+
+    #[test]
+    fn assert_cdata_type_checks_normalized_codes() -> LuaResult<()> {
+        let lua = Lua::new();
+        let storage = RawBox::new(0i32);
+        let value = LuaValue::Table(make_cdata_table(&lua, "int", storage.ptr() as *mut c_void)?);
+
+        let ptr = assert_cdata_type(&value, "int32")?;
+        assert_eq!(ptr, Some(storage.ptr() as *mut c_void));
+        assert!(assert_cdata_type(&value, " Const INT ").is_ok());
+
+        let err = assert_cdata_type(&value, "double").expect_err("int is not double");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Type)
+        );
+        assert!(assert_cdata_type(&LuaValue::Integer(1), "int").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn call_accepts_qualified_type_codes() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(
+            &lua,
+            "void * const",
+            &["const volatile void *restrict"],
+            false,
+            1,
+        )?;
+        let mut value = 0u8;
+        let target = &mut value as *mut u8 as *mut c_void;
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::LightUserData(LuaLightUserData(target))],
+        )?;
+        let func = LuaLightUserData(luneffi_test_identity_pointer as *const () as *mut c_void);
+        match call(&lua, func, signature, args)? {
+            LuaValue::LightUserData(ptr) => assert_eq!(ptr.0, target),
             other => panic!("unexpected result: {other:?}"),
         }
         Ok(())
     }
 
+    #[test]
+    fn call_passes_buffers_as_pointers_to_their_storage() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["pointer", "int32"], false, 2)?;
+        let buffer = lua.create_buffer(7i32.to_ne_bytes())?;
+
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Buffer(buffer.clone()), LuaValue::Integer(35)],
+        )?;
+        let func = LuaLightUserData(luneffi_test_counter_add as *const () as *mut c_void);
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+        assert_eq!(i32::from_ne_bytes(buffer.read_bytes::<4>(0)), 42);
+        Ok(())
+    }
+
+    #[test]
+    fn call_reports_structured_range_errors() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(1 << 40), LuaValue::Integer(1)])?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let err =
+            call(&lua, func, signature.clone(), args).expect_err("argument should be out of range");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Range);
+
+        let args = pack_args(
+            &lua,
+            vec![LuaValue::Integer(1), LuaValue::Integer(5_000_000_000)],
+        )?;
+        let err = call(&lua, func, signature, args).expect_err("argument should be out of range");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Range);
+        assert!(
+            ffi_err
+                .message()
+                .starts_with("argument 2 (int32): value 5000000000 out of range"),
+            "unexpected message: {}",
+            ffi_err.message()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn call_names_the_argument_when_a_scalar_is_nil() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(1), LuaValue::Nil])?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let err = call(&lua, func, signature, args).expect_err("nil is not an int");
+        let ffi_err = error::find(&err).expect("expected structured ffi error");
+        assert_eq!(ffi_err.kind(), error::ErrorKind::Type);
+        assert_eq!(ffi_err.message(), "argument 2 expected int32 but got nil");
+
+        let signature = make_signature(&lua, "pointer", &["pointer"], false, 1)?;
+        let func = LuaLightUserData(luneffi_test_identity_pointer as *const () as *mut c_void);
+        let result = call(&lua, func, signature, pack_args(&lua, vec![LuaValue::Nil])?)?;
+        assert!(matches!(result, LuaValue::Nil));
+        Ok(())
+    }
+
+    #[test]
+    fn call_timed_reports_result_and_duration() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let args = pack_args(&lua, vec![LuaValue::Integer(20), LuaValue::Integer(22)])?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let (result, nanos) = call_timed(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+        assert!(nanos < 1_000_000_000);
+        Ok(())
+    }
+
+    #[test]
+    fn call_many_reuses_signature_for_each_set() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32", "int32"], false, 2)?;
+        let sets = lua.create_table()?;
+        sets.raw_push(pack_args(
+            &lua,
+            vec![LuaValue::Integer(1), LuaValue::Integer(2)],
+        )?)?;
+        sets.raw_push(pack_args(
+            &lua,
+            vec![LuaValue::Integer(40), LuaValue::Integer(2)],
+        )?)?;
+        let func = LuaLightUserData(luneffi_test_add_ints as *const () as *mut c_void);
+        let results = call_many(&lua, func, signature, sets)?;
+        assert_eq!(results.raw_get::<i64>(1)?, 3);
+        assert_eq!(results.raw_get::<i64>(2)?, 42);
+        assert_eq!(results.get::<usize>("n")?, 2);
+        Ok(())
+    }
+
     #[test]
     fn call_variadic_sum_infers_arguments() -> LuaResult<()> {
         let lua = Lua::new();
@@ -696,6 +2785,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn strict_mode_rejects_lossy_argument_conversions() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["int32"], true, 1)?;
+        let func = LuaLightUserData(luneffi_test_variadic_sum as *const () as *mut c_void);
+        let flag_args = || pack_args(&lua, vec![LuaValue::Integer(1), LuaValue::Boolean(true)]);
+        let to_double = |value: i64| {
+            convert_typed_argument(
+                LuaValue::Integer(value),
+                &CType::primitive(TypeCode::Float64),
+                Overflow::Error,
+                &mut StringArgs::default(),
+            )
+        };
+
+        types::set_strict(true);
+        let flag = call(&lua, func, signature.clone(), flag_args()?);
+        let exact = to_double(1 << 53);
+        let inexact = to_double((1 << 53) + 1);
+        types::set_strict(false);
+
+        let err = flag.expect_err("strict mode should reject a variadic boolean");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Type)
+        );
+        assert!(exact.is_ok());
+        let err = inexact
+            .err()
+            .expect("strict mode should reject an inexact double");
+        assert_eq!(
+            error::find(&err).map(|err| err.kind()),
+            Some(error::ErrorKind::Range)
+        );
+
+        let result = call(&lua, func, signature, flag_args()?)?;
+        assert!(matches!(result, LuaValue::Integer(1)));
+        assert!(to_double((1 << 53) + 1).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn call_variadic_without_any_arguments() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &[], true, 0)?;
+        let func = LuaLightUserData(luneffi_test_variadic_none as *const () as *mut c_void);
+        let result = call(&lua, func, signature.clone(), pack_args(&lua, vec![])?)?;
+        assert!(matches!(result, LuaValue::Integer(7)));
+
+        let parsed = Signature::from_table(signature)?;
+        assert!(parsed.build_cif(&[]).is_ok());
+        let err = Signature::from_table(make_signature(&lua, "int32", &["int32"], true, 1)?)?
+            .build_cif(&[])
+            .err()
+            .expect("missing fixed argument types must be rejected");
+        assert!(err.to_string().contains("at least 1 fixed"));
+        Ok(())
+    }
+
+    #[test]
+    fn call_with_signature_from_builder() -> LuaResult<()> {
+        let lua = Lua::new();
+        let mut builder = SignatureBuilder::default();
+        assert!(builder.build(&lua).is_err());
+
+        builder.set_result("int32")?;
+        assert!(builder.set_result("int32").is_err());
+        assert!(builder.push_arg("void").is_err());
+        builder.push_arg("int32")?;
+        assert!(builder.set_variadic(Some(2)).is_err());
+        builder.set_variadic(None)?;
+        assert!(builder.push_arg("int32").is_err());
+
+        let signature = builder.build(&lua)?;
+        assert_eq!(signature.get::<usize>("fixedCount")?, 1);
+        let args = pack_args(
+            &lua,
+            vec![
+                LuaValue::Integer(2),
+                LuaValue::Integer(30),
+                LuaValue::Integer(12),
+            ],
+        )?;
+        let func = LuaLightUserData(luneffi_test_variadic_sum as *const () as *mut c_void);
+        let result = call(&lua, func, signature, args)?;
+        assert!(matches!(result, LuaValue::Integer(42)));
+        Ok(())
+    }
+
     #[test]
     fn call_variadic_format_handles_strings() -> LuaResult<()> {
         let lua = Lua::new();
@@ -729,6 +2907,68 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn call_variadic_honors_tagged_types() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["pointer", "size_t", "pointer"], true, 3)?;
+
+        let mut buffer: [c_char; 64] = [0; 64];
+        let format = lua.create_string("%u %.1f")?;
+
+        let args = pack_args(
+            &lua,
+            vec![
+                LuaValue::LightUserData(LuaLightUserData(buffer.as_mut_ptr() as *mut c_void)),
+                LuaValue::Integer(buffer.len() as i64),
+                LuaValue::String(format),
+                LuaValue::Integer(u32::MAX as i64),
+                LuaValue::Number(1.5),
+            ],
+        )?;
+        let variadic_types = lua.create_table()?;
+        variadic_types.raw_set(1, "uint32")?;
+        variadic_types.raw_set(2, "float")?;
+        args.set("variadicTypes", variadic_types)?;
+
+        let func = LuaLightUserData(luneffi_test_variadic_format as *const () as *mut c_void);
+        call(&lua, func, signature, args)?;
+
+        let c_str = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(c_str.to_str().unwrap(), format!("{} 1.5", u32::MAX));
+        Ok(())
+    }
+
+    #[test]
+    fn call_variadic_promotes_integers_tagged_as_floating() -> LuaResult<()> {
+        let lua = Lua::new();
+        let signature = make_signature(&lua, "int32", &["pointer", "size_t", "pointer"], true, 3)?;
+
+        let mut buffer: [c_char; 64] = [0; 64];
+        let format = lua.create_string("%f %f")?;
+
+        let args = pack_args(
+            &lua,
+            vec![
+                LuaValue::LightUserData(LuaLightUserData(buffer.as_mut_ptr() as *mut c_void)),
+                LuaValue::Integer(buffer.len() as i64),
+                LuaValue::String(format),
+                LuaValue::Integer(3),
+                LuaValue::Integer(-2),
+            ],
+        )?;
+        let variadic_types = lua.create_table()?;
+        variadic_types.raw_set(1, "double")?;
+        variadic_types.raw_set(2, "float")?;
+        args.set("variadicTypes", variadic_types)?;
+
+        let func = LuaLightUserData(luneffi_test_variadic_format as *const () as *mut c_void);
+        call(&lua, func, signature, args)?;
+
+        let c_str = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(c_str.to_str().unwrap(), "3.000000 -2.000000");
+        Ok(())
+    }
+
     #[test]
     fn call_variadic_uses_cdata_type_information() -> LuaResult<()> {
         let lua = Lua::new();
@@ -1,27 +1,112 @@
+use std::cell::{Cell, RefCell};
 use std::ffi::c_void;
 use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use libffi::middle::Closure;
 use mlua::RegistryKey;
 use mlua::prelude::*;
 
+use crate::error;
+use crate::native;
 use crate::signature::{CType, Signature};
 use crate::types::{self, TypeCode};
 
-const CALLBACK_RESULT_SIZE: usize = 16;
+const WEAK_CALLBACKS_KEY: &str = "luneffi.weak_callbacks";
+
+static LIVE_CALLBACKS: AtomicUsize = AtomicUsize::new(0);
+static NEXT_WEAK_SLOT: AtomicUsize = AtomicUsize::new(1);
+
+pub(crate) fn live_count() -> usize {
+    LIVE_CALLBACKS.load(Ordering::Relaxed)
+}
+
+enum FunctionRef {
+    Strong(RegistryKey),
+    // slot in a weak-valued registry table, so the callback does not keep the
+    // function alive
+    Weak(usize),
+}
+
+fn weak_callbacks(lua: &Lua) -> LuaResult<LuaTable> {
+    if let Ok(table) = lua.named_registry_value::<LuaTable>(WEAK_CALLBACKS_KEY) {
+        return Ok(table);
+    }
+    let table = lua.create_table()?;
+    let meta = lua.create_table()?;
+    meta.set("__mode", "v")?;
+    table.set_metatable(Some(meta))?;
+    lua.set_named_registry_value(WEAK_CALLBACKS_KEY, table.clone())?;
+    Ok(table)
+}
+
+impl FunctionRef {
+    fn is_weak(&self) -> bool {
+        matches!(self, FunctionRef::Weak(_))
+    }
+
+    fn new(lua: &Lua, func: LuaFunction, weak: bool) -> LuaResult<Self> {
+        if !weak {
+            return Ok(FunctionRef::Strong(lua.create_registry_value(func)?));
+        }
+        let slot = NEXT_WEAK_SLOT.fetch_add(1, Ordering::Relaxed);
+        weak_callbacks(lua)?.raw_set(slot, func)?;
+        Ok(FunctionRef::Weak(slot))
+    }
+
+    fn release(self, lua: &Lua) {
+        match self {
+            FunctionRef::Strong(key) => drop(key),
+            FunctionRef::Weak(slot) => {
+                if let Ok(table) = weak_callbacks(lua) {
+                    let _ = table.raw_set(slot, LuaValue::Nil);
+                }
+            }
+        }
+    }
+}
+
+// how a 64-bit integer argument reaches Lua; numbers round past 2^53
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum WideDelivery {
+    #[default]
+    Number,
+    String,
+    Box,
+}
 
 struct CallbackData {
     lua: Lua,
-    function_key: Option<RegistryKey>,
+    function_ref: RefCell<Option<FunctionRef>>,
     signature: Signature,
+    // Lua descriptors of arguments passed by value as structs, indexed like the
+    // signature's args, so the copies handed to Lua are typed cdata
+    struct_args: Vec<Option<RegistryKey>>,
+    // per-argument delivery of 64-bit integers, indexed like the signature's args
+    wide_args: Vec<WideDelivery>,
+    reported_dead: Cell<bool>,
+    // number of invocations currently running Lua code; the function cannot be
+    // swapped out from under them
+    active: Cell<usize>,
 }
 
 impl CallbackData {
-    fn new(lua: Lua, signature: Signature, function_key: RegistryKey) -> Self {
+    fn new(
+        lua: Lua,
+        signature: Signature,
+        struct_args: Vec<Option<RegistryKey>>,
+        wide_args: Vec<WideDelivery>,
+        function_ref: FunctionRef,
+    ) -> Self {
         Self {
             lua,
-            function_key: Some(function_key),
+            function_ref: RefCell::new(Some(function_ref)),
             signature,
+            struct_args,
+            wide_args,
+            reported_dead: Cell::new(false),
+            active: Cell::new(0),
         }
     }
 
@@ -29,12 +114,15 @@ impl CallbackData {
         &self.signature
     }
 
-    fn get_function(&self) -> LuaResult<LuaFunction> {
-        let key = self
-            .function_key
-            .as_ref()
-            .ok_or_else(|| LuaError::runtime("callback function has been released".to_string()))?;
-        self.lua.registry_value(key)
+    // None means a weak callback whose function has been collected
+    fn get_function(&self) -> LuaResult<Option<LuaFunction>> {
+        match &*self.function_ref.borrow() {
+            Some(FunctionRef::Strong(key)) => self.lua.registry_value(key).map(Some),
+            Some(FunctionRef::Weak(slot)) => weak_callbacks(&self.lua)?.raw_get(*slot),
+            None => Err(LuaError::runtime(
+                "callback function has been released".to_string(),
+            )),
+        }
     }
 
     fn read_argument(
@@ -45,11 +133,33 @@ impl CallbackData {
     ) -> LuaResult<LuaValue> {
         unsafe {
             let arg_ptr = *args.add(index);
+            let delivery = self.wide_args.get(index).copied().unwrap_or_default();
+            if delivery != WideDelivery::Number {
+                let bits = *(arg_ptr as *const u64);
+                let signed = is_signed_wide(ty.code());
+                return match delivery {
+                    WideDelivery::String if signed => Ok(LuaValue::String(
+                        self.lua.create_string((bits as i64).to_string())?,
+                    )),
+                    WideDelivery::String => {
+                        Ok(LuaValue::String(self.lua.create_string(bits.to_string())?))
+                    }
+                    _ => {
+                        let descriptor = if signed {
+                            "long long"
+                        } else {
+                            "unsigned long long"
+                        };
+                        native::box_integer(&self.lua, descriptor, bits)
+                    }
+                };
+            }
             match ty.code() {
-                TypeCode::Void => Err(LuaError::runtime(
-                    "void type cannot be used as a callback argument".to_string(),
+                TypeCode::Void => Err(error::type_error(
+                    "void type cannot be used as a callback argument",
                 )),
                 TypeCode::Int8 => Ok(LuaValue::Integer(*(arg_ptr as *const i8) as i64)),
+                TypeCode::Bool => Ok(LuaValue::Boolean(*(arg_ptr as *const u8) != 0)),
                 TypeCode::UInt8 => Ok(LuaValue::Integer(*(arg_ptr as *const u8) as i64)),
                 TypeCode::Int16 => Ok(LuaValue::Integer(*(arg_ptr as *const i16) as i64)),
                 TypeCode::UInt16 => Ok(LuaValue::Integer(*(arg_ptr as *const u16) as i64)),
@@ -58,11 +168,7 @@ impl CallbackData {
                 TypeCode::Int64 => Ok(LuaValue::Integer(*(arg_ptr as *const i64))),
                 TypeCode::UInt64 => {
                     let value = *(arg_ptr as *const u64);
-                    if value <= i64::MAX as u64 {
-                        Ok(LuaValue::Integer(value as i64))
-                    } else {
-                        Ok(LuaValue::Number(value as f64))
-                    }
+                    types::u64_to_lua(value)
                 }
                 TypeCode::IntPtr => {
                     if usize::BITS == 64 {
@@ -74,11 +180,7 @@ impl CallbackData {
                 TypeCode::UIntPtr => {
                     if usize::BITS == 64 {
                         let value = *(arg_ptr as *const u64);
-                        if value <= i64::MAX as u64 {
-                            Ok(LuaValue::Integer(value as i64))
-                        } else {
-                            Ok(LuaValue::Number(value as f64))
-                        }
+                        types::u64_to_lua(value)
                     } else {
                         Ok(LuaValue::Integer(*(arg_ptr as *const u32) as i64))
                     }
@@ -93,6 +195,27 @@ impl CallbackData {
                         Ok(LuaValue::LightUserData(LuaLightUserData(value)))
                     }
                 }
+                TypeCode::LongDouble => Err(types::long_double_unsupported()),
+                TypeCode::Struct => {
+                    let Some(Some(key)) = self.struct_args.get(index) else {
+                        return Err(error::type_error(format!(
+                            "missing struct descriptor for callback argument {}",
+                            index + 1
+                        )));
+                    };
+                    let descriptor: LuaValue = self.lua.registry_value(key)?;
+                    // the bytes belong to the caller's frame, so Lua gets its own copy
+                    let size = ty.size();
+                    let copy = libc::malloc(size.max(1));
+                    if copy.is_null() {
+                        return Err(error::range(format!(
+                            "failed to allocate {size} bytes for struct argument"
+                        )));
+                    }
+                    ptr::copy_nonoverlapping(arg_ptr as *const u8, copy as *mut u8, size);
+                    native::make_cdata(&self.lua, descriptor, copy, true)
+                        .inspect_err(|_| libc::free(copy))
+                }
             }
         }
     }
@@ -102,62 +225,50 @@ impl CallbackData {
             LuaValue::Nil => Ok(ptr::null_mut()),
             LuaValue::LightUserData(ptr) => Ok(ptr.0),
             LuaValue::Boolean(false) => Ok(ptr::null_mut()),
-            LuaValue::Boolean(true) => Err(LuaError::runtime(
-                "cannot convert boolean 'true' to pointer".to_string(),
+            LuaValue::Boolean(true) => Err(error::type_error(
+                "cannot convert boolean 'true' to pointer",
             )),
             LuaValue::Integer(i) => {
                 if *i < 0 {
-                    return Err(LuaError::runtime(
-                        "pointer value must be non-negative".to_string(),
-                    ));
+                    return Err(error::range("pointer value must be non-negative"));
                 }
                 Ok((*i as u64) as usize as *mut c_void)
             }
             LuaValue::Number(n) => {
                 if !n.is_finite() {
-                    return Err(LuaError::runtime(
-                        "pointer value must be finite".to_string(),
-                    ));
+                    return Err(error::range("pointer value must be finite"));
                 }
                 if *n < 0.0 {
-                    return Err(LuaError::runtime(
-                        "pointer value must be non-negative".to_string(),
-                    ));
+                    return Err(error::range("pointer value must be non-negative"));
                 }
                 if (n.trunc() - n).abs() > f64::EPSILON {
-                    return Err(LuaError::runtime(
-                        "pointer value must be integral".to_string(),
-                    ));
+                    return Err(error::type_error("pointer value must be integral"));
                 }
                 Ok((*n as u64) as usize as *mut c_void)
             }
             LuaValue::Table(table) => {
                 let marker = table.raw_get::<LuaValue>("__ffi_cdata")?;
                 if !matches!(marker, LuaValue::Boolean(true)) {
-                    return Err(LuaError::runtime(
-                        "cannot convert table value to pointer".to_string(),
-                    ));
+                    return Err(error::type_error("cannot convert table value to pointer"));
                 }
                 let inner = table.raw_get::<LuaValue>("__ptr")?;
                 match inner {
-                    LuaValue::LightUserData(ptr) => Ok(ptr.0),
+                    LuaValue::LightUserData(ptr) => {
+                        Ok(ptr.0.wrapping_byte_add(native::cdata_offset(table)?))
+                    }
                     LuaValue::Nil => Ok(ptr::null_mut()),
-                    other => Err(LuaError::runtime(format!(
+                    other => Err(error::type_error(format!(
                         "cdata object missing native pointer (found {other:?})",
                     ))),
                 }
             }
-            other => Err(LuaError::runtime(format!(
+            other => Err(error::type_error(format!(
                 "cannot convert value {other:?} to pointer",
             ))),
         }
     }
 
-    fn write_result(
-        &self,
-        buffer: &mut [u8; CALLBACK_RESULT_SIZE],
-        value: LuaValue,
-    ) -> LuaResult<()> {
+    fn write_result(&self, buffer: &mut [u8], value: LuaValue) -> LuaResult<()> {
         buffer.fill(0);
         match self.signature().result().code() {
             TypeCode::Void => Ok(()),
@@ -166,6 +277,11 @@ impl CallbackData {
                 buffer[..1].copy_from_slice(&v.to_ne_bytes());
                 Ok(())
             }
+            TypeCode::Bool => {
+                let v = types::lua_value_to_bool(&value)?;
+                buffer[..1].copy_from_slice(&u8::from(v).to_ne_bytes());
+                Ok(())
+            }
             TypeCode::UInt8 => {
                 let v = types::clamp_unsigned(types::lua_value_to_u64(&value)?, 8)? as u8;
                 buffer[..1].copy_from_slice(&v.to_ne_bytes());
@@ -226,7 +342,7 @@ impl CallbackData {
             TypeCode::Float32 => {
                 let v = match value {
                     LuaValue::Number(n) => n as f32,
-                    LuaValue::Integer(i) => i as f32,
+                    LuaValue::Integer(i) => types::integer_to_f32(i)?,
                     LuaValue::Boolean(b) => {
                         if b {
                             1.0
@@ -235,7 +351,7 @@ impl CallbackData {
                         }
                     }
                     other => {
-                        return Err(LuaError::runtime(format!(
+                        return Err(error::type_error(format!(
                             "expected numeric value for float result, got {other:?}"
                         )));
                     }
@@ -246,7 +362,7 @@ impl CallbackData {
             TypeCode::Float64 => {
                 let v = match value {
                     LuaValue::Number(n) => n,
-                    LuaValue::Integer(i) => i as f64,
+                    LuaValue::Integer(i) => types::integer_to_f64(i)?,
                     LuaValue::Boolean(b) => {
                         if b {
                             1.0
@@ -255,7 +371,7 @@ impl CallbackData {
                         }
                     }
                     other => {
-                        return Err(LuaError::runtime(format!(
+                        return Err(error::type_error(format!(
                             "expected numeric value for double result, got {other:?}"
                         )));
                     }
@@ -270,34 +386,124 @@ impl CallbackData {
                 buffer[..size].copy_from_slice(&bytes[..size]);
                 Ok(())
             }
+            TypeCode::LongDouble => Err(types::long_double_unsupported()),
+            TypeCode::Struct => {
+                let size = self.signature().result().size();
+                if buffer.len() < size {
+                    return Err(error::range(format!(
+                        "struct result needs {size} bytes but the result buffer holds {}",
+                        buffer.len()
+                    )));
+                }
+                let source = self.struct_result_source(&value, size)?;
+                unsafe {
+                    ptr::copy_nonoverlapping(source as *const u8, buffer.as_mut_ptr(), size);
+                }
+                Ok(())
+            }
         }
     }
 
-    fn invoke(
-        &mut self,
-        result: &mut [u8; CALLBACK_RESULT_SIZE],
-        args: *const *const c_void,
-    ) -> LuaResult<()> {
+    // a struct result must come back as cdata holding at least the declared bytes
+    fn struct_result_source(&self, value: &LuaValue, size: usize) -> LuaResult<*const c_void> {
+        let LuaValue::Table(table) = value else {
+            return Err(error::type_error(format!(
+                "expected cdata for struct result, got {value:?}"
+            )));
+        };
+        if !matches!(
+            table.raw_get::<LuaValue>("__ffi_cdata")?,
+            LuaValue::Boolean(true)
+        ) {
+            return Err(error::type_error("expected cdata for struct result"));
+        }
+        // an offset cdata only has the bytes past its offset to give
+        let offset = native::cdata_offset(table)?;
+        if let Some(ctype) = table.raw_get::<Option<LuaTable>>("__ctype")? {
+            if let Some(actual) = ctype.get::<Option<usize>>("size")? {
+                let actual = actual.saturating_sub(offset);
+                if actual < size {
+                    return Err(error::type_error(format!(
+                        "cdata of {actual} bytes cannot be returned as a {size}-byte struct"
+                    )));
+                }
+            }
+        }
+        match table.raw_get::<LuaValue>("__ptr")? {
+            LuaValue::LightUserData(ptr) if !ptr.0.is_null() => Ok(ptr.0.wrapping_byte_add(offset)),
+            _ => Err(error::null_pointer("struct result cdata has no storage")),
+        }
+    }
+
+    // libffi sizes the result storage from the cif: scalars get at least one
+    // `ffi_arg`, since small integers travel widened to a full register, while
+    // records get exactly their own size (for large records that is the caller's
+    // memory, so writing past it would corrupt their frame)
+    fn result_size(&self) -> usize {
+        let result = self.signature().result();
+        match result.code() {
+            TypeCode::Void => 0,
+            TypeCode::Struct => result.size(),
+            _ => result
+                .size()
+                .max(std::mem::size_of::<libffi::raw::ffi_arg>()),
+        }
+    }
+
+    fn set_function(&self, func: LuaFunction) -> LuaResult<()> {
+        if self.active.get() > 0 {
+            return Err(error::type_error(
+                "cannot replace a callback's function while it is running",
+            ));
+        }
+        let weak = match &*self.function_ref.borrow() {
+            Some(function_ref) => function_ref.is_weak(),
+            None => {
+                return Err(LuaError::runtime(
+                    "callback function has been released".to_string(),
+                ));
+            }
+        };
+        let replacement = FunctionRef::new(&self.lua, func, weak)?;
+        if let Some(previous) = self.function_ref.replace(Some(replacement)) {
+            previous.release(&self.lua);
+        }
+        self.reported_dead.set(false);
+        Ok(())
+    }
+
+    fn invoke(&self, result: &mut [u8], args: *const *const c_void) -> LuaResult<()> {
         let mut values = Vec::with_capacity(self.signature().args().len());
         for (index, ty) in self.signature().args().iter().enumerate() {
             let value = self.read_argument(args, index, ty)?;
             values.push(value);
         }
         let lua_args = LuaMultiValue::from_vec(values);
-        let callback = self.get_function()?;
-        let returned = callback.call::<LuaValue>(lua_args)?;
-        self.write_result(result, returned)
+        let Some(callback) = self.get_function()? else {
+            // the weak function was collected: leave the zeroed result and say so once
+            if !self.reported_dead.replace(true) {
+                self.warn("ffi: weak callback invoked after its function was collected");
+            }
+            return Ok(());
+        };
+        self.active.set(self.active.get() + 1);
+        let returned = callback.call::<LuaValue>(lua_args);
+        self.active.set(self.active.get() - 1);
+        self.write_result(result, returned?)
     }
 
-    fn report_error(&self, err: LuaError) {
-        let message = format!("ffi: error in callback: {err}");
+    fn warn(&self, message: &str) {
         let globals = self.lua.globals();
         if let Ok(warn) = globals.get::<LuaFunction>("warn") {
-            let _ = warn.call::<()>(message.clone());
+            let _ = warn.call::<()>(message);
             return;
         }
         eprintln!("{message}");
     }
+
+    fn report_error(&self, err: LuaError) {
+        self.warn(&format!("ffi: error in callback: {err}"));
+    }
 }
 
 struct CallbackHandle {
@@ -309,22 +515,22 @@ impl CallbackHandle {
     fn new(
         lua: &Lua,
         signature: Signature,
+        struct_args: Vec<Option<RegistryKey>>,
+        wide_args: Vec<WideDelivery>,
         func: LuaFunction,
+        weak: bool,
     ) -> LuaResult<(Self, LuaLightUserData)> {
-        if signature.is_variadic() {
-            return Err(LuaError::runtime(
-                "TODO(@lune/ffi/callback): variadic callbacks not supported yet".to_string(),
-            ));
-        }
+        validate_signature(&signature)?;
 
         let arg_types = signature.arg_types();
-        let cif = signature.build_cif(&arg_types);
-        let registry_key = lua.create_registry_value(func)?;
-        let data = CallbackData::new(lua.clone(), signature, registry_key);
+        let cif = signature.build_cif(&arg_types)?;
+        let function_ref = FunctionRef::new(lua, func, weak)?;
+        let data = CallbackData::new(lua.clone(), signature, struct_args, wide_args, function_ref);
         let data_ptr = Box::into_raw(Box::new(data));
-        let closure = Closure::new_mut(cif, callback_trampoline, unsafe { &mut *data_ptr });
+        let closure = Closure::new(cif, callback_trampoline, unsafe { &*data_ptr });
         let code_ptr = closure.code_ptr();
         let raw_ptr = *code_ptr as *const () as *mut c_void;
+        LIVE_CALLBACKS.fetch_add(1, Ordering::Relaxed);
         Ok((
             Self {
                 closure: Some(closure),
@@ -335,30 +541,171 @@ impl CallbackHandle {
     }
 }
 
+impl CallbackHandle {
+    // persistent callbacks stay valid for the rest of the process, so they stop counting
+    // as live callbacks that would keep a library from closing
+    fn leak(self) {
+        LIVE_CALLBACKS.fetch_sub(1, Ordering::Relaxed);
+        std::mem::forget(self);
+    }
+}
+
 impl Drop for CallbackHandle {
     fn drop(&mut self) {
         unsafe {
             if let Some(closure) = self.closure.take() {
                 drop(closure);
+                LIVE_CALLBACKS.fetch_sub(1, Ordering::Relaxed);
             }
             if !self.data.is_null() {
-                let mut data = Box::from_raw(self.data);
-                if let Some(key) = data.function_key.take() {
-                    drop(key);
+                let data = Box::from_raw(self.data);
+                if let Some(function_ref) = data.function_ref.take() {
+                    function_ref.release(&data.lua);
                 }
             }
         }
     }
 }
 
-impl LuaUserData for CallbackHandle {}
+impl LuaUserData for CallbackHandle {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("setFunction", |_, this, func: LuaFunction| {
+            // the trampoline keeps pointing at the same data, so C sees no change
+            unsafe { &*this.data }.set_function(func)
+        });
+    }
+}
+
+// rejects signatures the trampoline could only answer with a warning and a zeroed
+// result on every call, so the mistake surfaces when the callback is created
+fn validate_signature(signature: &Signature) -> LuaResult<()> {
+    if signature.is_variadic() {
+        return Err(error::type_error(
+            "TODO(@lune/ffi/callback): variadic callbacks not supported yet (the trampoline \
+             cannot see how many extra arguments C passed)",
+        ));
+    }
+    for (index, ty) in signature.args().iter().enumerate() {
+        if ty.code() == TypeCode::LongDouble {
+            return Err(error::type_error(format!(
+                "callback argument {} is a long double, which callbacks cannot receive yet",
+                index + 1
+            )));
+        }
+    }
+    validate_result(signature)
+}
+
+const SUPPORTED_RESULTS: &str = "void, bool, 8/16/32/64-bit integers, intptr_t, uintptr_t, \
+                                 float, double, pointers, and structs or unions with a known layout";
+
+// the result types `write_result` can encode; anything else would reach C as zeroed bytes
+fn validate_result(signature: &Signature) -> LuaResult<()> {
+    let result = signature.result();
+    let unsupported = match result.code() {
+        TypeCode::LongDouble => Some("callbacks cannot return long double values yet"),
+        TypeCode::Struct if result.size() == 0 => {
+            Some("callbacks cannot return a struct whose layout is unknown")
+        }
+        _ => None,
+    };
+    match unsupported {
+        Some(reason) => Err(error::type_error(format!(
+            "{reason}; supported result types are {SUPPORTED_RESULTS}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn struct_descriptors(
+    lua: &Lua,
+    signature: &Signature,
+    signature_table: &LuaTable,
+) -> LuaResult<Vec<Option<RegistryKey>>> {
+    let declared: LuaTable = signature_table.get("args")?;
+    // member functions carry an implicit object pointer that is not in the table
+    let leading = signature.args().len().saturating_sub(declared.raw_len());
+    let mut descriptors = Vec::with_capacity(signature.args().len());
+    for (index, ty) in signature.args().iter().enumerate() {
+        if ty.code() != TypeCode::Struct || index < leading {
+            descriptors.push(None);
+            continue;
+        }
+        let descriptor: LuaValue = declared.raw_get(index - leading + 1)?;
+        descriptors.push(Some(lua.create_registry_value(descriptor)?));
+    }
+    Ok(descriptors)
+}
+
+fn is_signed_wide(code: TypeCode) -> bool {
+    matches!(code, TypeCode::Int64 | TypeCode::IntPtr)
+}
+
+fn is_wide_integer(code: TypeCode) -> bool {
+    match code {
+        TypeCode::Int64 | TypeCode::UInt64 => true,
+        TypeCode::IntPtr | TypeCode::UIntPtr => usize::BITS == 64,
+        _ => false,
+    }
+}
+
+// `wideArgs = { [n] = "string" | "box" }` hands the nth declared argument to Lua as a
+// decimal string or a `long long`/`unsigned long long` cdata instead of a number
+fn wide_deliveries(
+    signature: &Signature,
+    signature_table: &LuaTable,
+    options: Option<&LuaTable>,
+) -> LuaResult<Vec<WideDelivery>> {
+    let mut deliveries = vec![WideDelivery::Number; signature.args().len()];
+    let Some(modes) = options
+        .map(|options| options.get::<Option<LuaTable>>("wideArgs"))
+        .transpose()?
+        .flatten()
+    else {
+        return Ok(deliveries);
+    };
+
+    let declared: LuaTable = signature_table.get("args")?;
+    let leading = signature.args().len().saturating_sub(declared.raw_len());
+    for pair in modes.pairs::<usize, String>() {
+        let (position, mode) = pair?;
+        let index = position
+            .checked_sub(1)
+            .map(|index| index + leading)
+            .filter(|&index| index < signature.args().len())
+            .ok_or_else(|| {
+                error::range(format!(
+                    "wideArgs names argument {position}, which does not exist"
+                ))
+            })?;
+        let code = signature.args()[index].code();
+        if !is_wide_integer(code) {
+            return Err(error::type_error(format!(
+                "wideArgs names argument {position}, which is '{}' rather than a 64-bit integer",
+                code.name()
+            )));
+        }
+        deliveries[index] = match mode.as_str() {
+            "number" => WideDelivery::Number,
+            "string" => WideDelivery::String,
+            "box" => WideDelivery::Box,
+            other => {
+                return Err(error::type_error(format!(
+                    "unknown wideArgs mode '{other}' (expected 'number', 'string', or 'box')"
+                )));
+            }
+        };
+    }
+    Ok(deliveries)
+}
 
 unsafe extern "C" fn callback_trampoline(
     _cif: &libffi::low::ffi_cif,
-    result: &mut [u8; CALLBACK_RESULT_SIZE],
+    result: &mut u8,
     args: *const *const c_void,
-    userdata: &mut CallbackData,
+    userdata: &CallbackData,
 ) {
+    let result = unsafe { slice::from_raw_parts_mut(result as *mut u8, userdata.result_size()) };
     result.fill(0);
     if let Err(err) = userdata.invoke(result, args) {
         userdata.report_error(err);
@@ -366,16 +713,36 @@ unsafe extern "C" fn callback_trampoline(
 }
 
 pub fn register(lua: &Lua, exports: &LuaTable) -> LuaResult<()> {
-    let factory =
-        lua.create_function(|lua, (signature_table, func): (LuaTable, LuaFunction)| {
-            let signature = Signature::from_table(signature_table)?;
-            let (handle, ptr) = CallbackHandle::new(lua, signature, func)?;
+    let factory = lua.create_function(
+        |lua, (signature_table, func, options): (LuaTable, LuaFunction, Option<LuaTable>)| {
+            let signature = Signature::from_table(signature_table.clone())?;
+            let struct_args = struct_descriptors(lua, &signature, &signature_table)?;
+            let wide_args = wide_deliveries(&signature, &signature_table, options.as_ref())?;
+            let (persistent, weak) = match options {
+                Some(options) => (
+                    options.get::<Option<bool>>("persistent")?.unwrap_or(false)
+                        || options.get::<Option<bool>>("leak")?.unwrap_or(false),
+                    options.get::<Option<bool>>("weak")?.unwrap_or(false),
+                ),
+                None => (false, false),
+            };
+
+            let (handle, ptr) =
+                CallbackHandle::new(lua, signature, struct_args, wide_args, func, weak)?;
+            if persistent {
+                // Intentionally leaked: the trampoline and the registry reference to the
+                // Lua function stay valid for the rest of the process.
+                handle.leak();
+                return Ok(LuaMultiValue::from_vec(vec![LuaValue::LightUserData(ptr)]));
+            }
+
             let userdata = lua.create_userdata(handle)?;
             Ok(LuaMultiValue::from_vec(vec![
                 LuaValue::LightUserData(ptr),
                 LuaValue::UserData(userdata),
             ]))
-        })?;
+        },
+    )?;
 
     exports.set("createCallback", factory)?;
     Ok(())
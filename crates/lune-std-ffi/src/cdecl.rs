@@ -0,0 +1,297 @@
+use mlua::prelude::*;
+
+use crate::error;
+use crate::types::{self, TypeCode};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Star,
+    LParen,
+    RParen,
+    Comma,
+    Ellipsis,
+    Semicolon,
+    End,
+}
+
+#[derive(Debug)]
+struct Spanned {
+    token: Token,
+    // 1-based column of the first character
+    column: usize,
+}
+
+fn malformed(column: usize, message: impl std::fmt::Display) -> LuaError {
+    error::type_error(format!(
+        "malformed C declaration at column {column}: {message}"
+    ))
+}
+
+fn tokenize(source: &str) -> LuaResult<Vec<Spanned>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let column = index + 1;
+        let token = match byte {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                index += 1;
+                continue;
+            }
+            b'*' => Token::Star,
+            b'(' => Token::LParen,
+            b')' => Token::RParen,
+            b',' => Token::Comma,
+            b';' => Token::Semicolon,
+            b'.' => {
+                if !source[index..].starts_with("...") {
+                    return Err(malformed(column, "expected '...'"));
+                }
+                index += 3;
+                tokens.push(Spanned {
+                    token: Token::Ellipsis,
+                    column,
+                });
+                continue;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                let start = index;
+                while index < bytes.len()
+                    && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'_')
+                {
+                    index += 1;
+                }
+                tokens.push(Spanned {
+                    token: Token::Ident(source[start..index].to_string()),
+                    column,
+                });
+                continue;
+            }
+            _ => {
+                let ch = source[index..].chars().next().unwrap_or('?');
+                return Err(malformed(column, format!("unexpected character '{ch}'")));
+            }
+        };
+        tokens.push(Spanned { token, column });
+        index += 1;
+    }
+
+    tokens.push(Spanned {
+        token: Token::End,
+        column: source.len() + 1,
+    });
+    Ok(tokens)
+}
+
+const QUALIFIERS: &[&str] = &["const", "volatile", "restrict", "struct"];
+
+const TYPE_WORDS: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned",
+];
+
+// spells out C base types in the vocabulary `TypeCode::from_code` understands
+fn canonical_code(words: &[&str]) -> Option<&'static str> {
+    let code = match words {
+        ["void"] => "void",
+        ["bool"] | ["_Bool"] => "bool",
+        ["char"] => "char",
+        ["signed", "char"] => "int8",
+        ["unsigned", "char"] => "uint8",
+        ["short"] | ["short", "int"] | ["signed", "short"] | ["signed", "short", "int"] => "int16",
+        ["unsigned", "short"] | ["unsigned", "short", "int"] => "uint16",
+        ["int"] | ["signed"] | ["signed", "int"] => "int",
+        ["unsigned"] | ["unsigned", "int"] => "unsigned int",
+        ["long"] | ["long", "int"] | ["signed", "long"] | ["signed", "long", "int"] => "long",
+        ["unsigned", "long"] | ["unsigned", "long", "int"] => "unsigned long",
+        ["long", "long"] | ["long", "long", "int"] | ["signed", "long", "long"] => "long long",
+        ["unsigned", "long", "long"] | ["unsigned", "long", "long", "int"] => "unsigned long long",
+        ["float"] => "float",
+        ["double"] => "double",
+        ["long", "double"] => "long double",
+        ["int8_t"] => "int8",
+        ["uint8_t"] => "uint8",
+        ["int16_t"] => "int16",
+        ["uint16_t"] => "uint16",
+        ["int32_t"] => "int32",
+        ["uint32_t"] => "uint32",
+        ["int64_t"] => "int64",
+        ["uint64_t"] => "uint64",
+        _ => return None,
+    };
+    Some(code)
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+}
+
+struct ParsedType {
+    code: String,
+    column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Spanned {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> &Spanned {
+        let token = &self.tokens[self.position];
+        if token.token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> LuaResult<()> {
+        let next = self.advance();
+        if next.token != expected {
+            return Err(malformed(next.column, format!("expected {what}")));
+        }
+        Ok(())
+    }
+
+    // reads a type followed by an optional declarator name; `(` stops the name so the
+    // function name can be picked up by the caller
+    fn parse_type(&mut self) -> LuaResult<(ParsedType, Option<String>)> {
+        let column = self.peek().column;
+        let mut words: Vec<String> = Vec::new();
+        let mut pointer_depth = 0;
+        let mut name = None;
+
+        loop {
+            let next = self.peek();
+            let at = next.column;
+            match next.token.clone() {
+                Token::Ident(word) if QUALIFIERS.contains(&word.as_str()) => {}
+                Token::Ident(word) => {
+                    if name.is_some() {
+                        return Err(malformed(at, format!("unexpected '{word}'")));
+                    }
+                    // a trailing identifier that cannot extend the type names the declarator
+                    let extends_type = pointer_depth == 0
+                        && (words.is_empty() || TYPE_WORDS.contains(&word.as_str()));
+                    if extends_type {
+                        words.push(word);
+                    } else {
+                        name = Some(word);
+                    }
+                }
+                Token::Star => {
+                    if words.is_empty() {
+                        return Err(malformed(at, "expected a type before '*'"));
+                    }
+                    if name.is_some() {
+                        return Err(malformed(at, "unexpected '*'"));
+                    }
+                    pointer_depth += 1;
+                }
+                _ => break,
+            }
+            self.advance();
+        }
+
+        if words.is_empty() {
+            return Err(malformed(column, "expected a type"));
+        }
+
+        let code = if pointer_depth > 0 {
+            "pointer".to_string()
+        } else {
+            let borrowed: Vec<&str> = words.iter().map(String::as_str).collect();
+            let code = match canonical_code(&borrowed) {
+                Some(code) => code.to_string(),
+                None => types::normalize_code(&words.join(" ")),
+            };
+            if TypeCode::from_code(&code).is_err() {
+                return Err(malformed(
+                    column,
+                    format!("unsupported type '{}'", words.join(" ")),
+                ));
+            }
+            code
+        };
+
+        Ok((ParsedType { code, column }, name))
+    }
+}
+
+pub fn parse(lua: &Lua, source: &str) -> LuaResult<LuaTable> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+
+    // C11 `_Noreturn` (or `noreturn` from <stdnoreturn.h>) may lead the declaration
+    let noreturn = match &parser.peek().token {
+        Token::Ident(word) => word == "_Noreturn" || word == "noreturn",
+        _ => false,
+    };
+    if noreturn {
+        parser.advance();
+    }
+    let (result, name) = parser.parse_type()?;
+    let name = name.ok_or_else(|| malformed(parser.peek().column, "expected a function name"))?;
+    parser.expect(Token::LParen, "'('")?;
+
+    let mut args: Vec<String> = Vec::new();
+    let mut variadic = false;
+
+    let is_void_list = parser.peek().token == Token::Ident("void".to_string())
+        && parser.tokens[parser.position + 1].token == Token::RParen;
+    if is_void_list {
+        parser.advance();
+    } else if parser.peek().token != Token::RParen {
+        loop {
+            if parser.peek().token == Token::Ellipsis {
+                parser.advance();
+                variadic = true;
+                break;
+            }
+
+            let (arg, _) = parser.parse_type()?;
+            if arg.code == "void" {
+                return Err(malformed(arg.column, "'void' cannot be a parameter type"));
+            }
+            args.push(arg.code);
+
+            if parser.peek().token != Token::Comma {
+                break;
+            }
+            parser.advance();
+        }
+    }
+
+    parser.expect(Token::RParen, "')'")?;
+    if parser.peek().token == Token::Semicolon {
+        parser.advance();
+    }
+    let trailing = parser.peek();
+    if trailing.token != Token::End {
+        return Err(malformed(
+            trailing.column,
+            "unexpected input after declaration",
+        ));
+    }
+
+    let signature = lua.create_table()?;
+    signature.set("name", name)?;
+    signature.set("result", result.code)?;
+    signature.set("fixedCount", args.len())?;
+    signature.set("args", lua.create_sequence_from(args)?)?;
+    signature.set("variadic", variadic)?;
+    if noreturn {
+        signature.set("noreturn", true)?;
+    }
+    Ok(signature)
+}
+
+pub fn register(lua: &Lua, exports: &LuaTable) -> LuaResult<()> {
+    let parse_fn = lua.create_function(|lua, source: String| parse(lua, &source))?;
+    exports.set("parseCDecl", parse_fn)?;
+    Ok(())
+}
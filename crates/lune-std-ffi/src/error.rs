@@ -0,0 +1,192 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::fmt;
+
+use mlua::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    Load,
+    Symbol,
+    Type,
+    Range,
+    NullPointer,
+    Call,
+}
+
+impl ErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Load => "load",
+            ErrorKind::Symbol => "symbol",
+            ErrorKind::Type => "type",
+            ErrorKind::Range => "range",
+            ErrorKind::NullPointer => "nullPointer",
+            ErrorKind::Call => "call",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FfiError {
+    kind: ErrorKind,
+    message: String,
+    errno: Option<i32>,
+}
+
+impl FfiError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            errno: None,
+        }
+    }
+
+    pub fn with_errno(mut self, errno: i32) -> Self {
+        self.errno = Some(errno);
+        self
+    }
+
+    pub fn errno(&self) -> Option<i32> {
+        self.errno
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FfiError {}
+
+impl From<FfiError> for LuaError {
+    fn from(err: FfiError) -> Self {
+        LuaError::external(err)
+    }
+}
+
+pub fn load(message: impl Into<String>) -> LuaError {
+    FfiError::new(ErrorKind::Load, message).into()
+}
+
+pub fn symbol(message: impl Into<String>) -> LuaError {
+    FfiError::new(ErrorKind::Symbol, message).into()
+}
+
+pub fn type_error(message: impl Into<String>) -> LuaError {
+    FfiError::new(ErrorKind::Type, message).into()
+}
+
+pub fn range(message: impl Into<String>) -> LuaError {
+    FfiError::new(ErrorKind::Range, message).into()
+}
+
+pub fn null_pointer(message: impl Into<String>) -> LuaError {
+    FfiError::new(ErrorKind::NullPointer, message).into()
+}
+
+pub fn call(message: impl Into<String>, errno: Option<i32>) -> LuaError {
+    call_failure(message, errno).into()
+}
+
+pub fn call_failure(message: impl Into<String>, errno: Option<i32>) -> FfiError {
+    let err = FfiError::new(ErrorKind::Call, message);
+    match errno {
+        Some(errno) => err.with_errno(errno),
+        None => err,
+    }
+}
+
+thread_local! {
+    // failures that were recorded instead of raised, for check-after callers
+    static LAST_ERROR: RefCell<Option<FfiError>> = const { RefCell::new(None) };
+}
+
+pub fn record(err: FfiError) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(err));
+}
+
+pub fn last() -> Option<FfiError> {
+    LAST_ERROR.with(|last| last.borrow().clone())
+}
+
+pub fn clear() {
+    LAST_ERROR.with(|last| last.borrow_mut().take());
+}
+
+fn describe(lua: &Lua, err: &FfiError) -> LuaResult<LuaMultiValue> {
+    let mut values = vec![
+        LuaValue::String(lua.create_string(err.kind().as_str())?),
+        LuaValue::String(lua.create_string(err.message())?),
+    ];
+    if let Some(errno) = err.errno() {
+        values.push(LuaValue::Integer(errno.into()));
+        values.push(LuaValue::String(lua.create_string(strerror(errno))?));
+    }
+    Ok(LuaMultiValue::from_vec(values))
+}
+
+pub fn strerror(errno: i32) -> String {
+    let ptr = unsafe { libc::strerror(errno) };
+    if ptr.is_null() {
+        return format!("errno {errno}");
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn find(err: &LuaError) -> Option<&FfiError> {
+    match err {
+        LuaError::CallbackError { cause, .. } => find(cause),
+        LuaError::WithContext { cause, .. } => find(cause),
+        other => other.downcast_ref::<FfiError>(),
+    }
+}
+
+// failures caught before or right after the foreign call that leave the process in a
+// known state; load and symbol failures and script errors are not among them
+pub fn is_recoverable(err: &LuaError) -> bool {
+    find(err).is_some_and(|err| {
+        matches!(
+            err.kind(),
+            ErrorKind::Type | ErrorKind::Range | ErrorKind::NullPointer | ErrorKind::Call
+        )
+    })
+}
+
+pub fn register(lua: &Lua, exports: &LuaTable) -> LuaResult<()> {
+    let describe_fn = lua.create_function(|lua, value: LuaValue| {
+        let LuaValue::Error(err) = value else {
+            return Ok(LuaMultiValue::new());
+        };
+        match find(&err) {
+            Some(ffi_err) => describe(lua, ffi_err),
+            None => Ok(LuaMultiValue::new()),
+        }
+    })?;
+    exports.set("describeError", describe_fn)?;
+
+    let last_fn = lua.create_function(|lua, ()| match last() {
+        Some(err) => describe(lua, &err),
+        None => Ok(LuaMultiValue::new()),
+    })?;
+    exports.set("lastError", last_fn)?;
+
+    let clear_fn = lua.create_function(|_, ()| {
+        clear();
+        Ok(())
+    })?;
+    exports.set("clearError", clear_fn)?;
+    Ok(())
+}
@@ -4,6 +4,9 @@ use mlua::prelude::*;
 
 mod call;
 mod callback;
+mod cdecl;
+mod error;
+mod memory;
 mod native;
 mod signature;
 mod types;
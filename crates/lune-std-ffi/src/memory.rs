@@ -0,0 +1,366 @@
+use std::ffi::c_void;
+use std::slice;
+use std::sync::atomic::{self, AtomicU32, AtomicU64, Ordering};
+
+use mlua::prelude::*;
+
+use crate::error;
+use crate::types::{self, Overflow, TypeCode};
+
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+fn region<'a>(ptr: *mut c_void, count: u64, action: &str) -> LuaResult<&'a mut [u8]> {
+    if ptr.is_null() {
+        return Err(error::null_pointer(format!(
+            "attempt to {action} null pointer"
+        )));
+    }
+    let count =
+        usize::try_from(count).map_err(|_| error::range("byte count does not fit usize"))?;
+    Ok(unsafe { slice::from_raw_parts_mut(ptr as *mut u8, count) })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (CRC32_POLYNOMIAL & mask);
+        }
+    }
+    !crc
+}
+
+fn additive(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0u32, |sum, &byte| sum.wrapping_add(u32::from(byte)))
+}
+
+struct BitSpan {
+    first: usize,
+    len: usize,
+    shift: u32,
+    mask: u128,
+}
+
+fn bit_span(offset_bits: u64, width: u32) -> LuaResult<BitSpan> {
+    if width == 0 || width > 64 {
+        return Err(error::range(format!(
+            "bit width must be between 1 and 64 (got {width})"
+        )));
+    }
+    let first = usize::try_from(offset_bits / 8)
+        .map_err(|_| error::range("bit offset does not fit usize"))?;
+    let within = (offset_bits % 8) as u32;
+    let len = (within + width).div_ceil(8) as usize;
+    // bits are numbered in the order C compilers allocate bitfields: from the least
+    // significant bit on little-endian targets and from the most significant on big-endian
+    let shift = if cfg!(target_endian = "little") {
+        within
+    } else {
+        len as u32 * 8 - within - width
+    };
+    Ok(BitSpan {
+        first,
+        len,
+        shift,
+        mask: ((1u128 << width) - 1) << shift,
+    })
+}
+
+fn read_span(bytes: &[u8]) -> u128 {
+    if cfg!(target_endian = "little") {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u128, |acc, &byte| (acc << 8) | u128::from(byte))
+    } else {
+        bytes
+            .iter()
+            .fold(0u128, |acc, &byte| (acc << 8) | u128::from(byte))
+    }
+}
+
+fn write_span(bytes: &mut [u8], mut value: u128) {
+    if cfg!(target_endian = "little") {
+        for byte in bytes.iter_mut() {
+            *byte = value as u8;
+            value >>= 8;
+        }
+    } else {
+        for byte in bytes.iter_mut().rev() {
+            *byte = value as u8;
+            value >>= 8;
+        }
+    }
+}
+
+fn ordering(order: Option<&str>, operation: &str, refused: &[&str]) -> LuaResult<Ordering> {
+    let name = order.unwrap_or("seqcst");
+    if refused.contains(&name) {
+        return Err(error::type_error(format!(
+            "{operation} cannot use '{name}' ordering"
+        )));
+    }
+    match name {
+        "relaxed" => Ok(Ordering::Relaxed),
+        "acquire" => Ok(Ordering::Acquire),
+        "release" => Ok(Ordering::Release),
+        "acqrel" => Ok(Ordering::AcqRel),
+        "seqcst" => Ok(Ordering::SeqCst),
+        other => Err(error::type_error(format!(
+            "unknown memory order '{other}' (expected 'relaxed', 'acquire', 'release', \
+             'acqrel', or 'seqcst')"
+        ))),
+    }
+}
+
+// a relaxed fence orders nothing
+fn fence_ordering(order: Option<&str>) -> LuaResult<Ordering> {
+    ordering(order, "a fence", &["relaxed"])
+}
+
+struct AtomicSlot {
+    ptr: *mut c_void,
+    bits: u32,
+    signed: bool,
+}
+
+fn atomic_slot(ptr: *mut c_void, code: &str) -> LuaResult<AtomicSlot> {
+    let ty = TypeCode::from_code(&types::normalize_code(code))?;
+    let (bits, signed) = match ty {
+        TypeCode::Int32 => (32, true),
+        TypeCode::UInt32 => (32, false),
+        TypeCode::Int64 => (64, true),
+        TypeCode::UInt64 => (64, false),
+        TypeCode::IntPtr => (usize::BITS, true),
+        TypeCode::UIntPtr => (usize::BITS, false),
+        _ => {
+            return Err(error::type_error(format!(
+                "atomic operations need a 32-bit or 64-bit integer type, got '{}'",
+                ty.name()
+            )));
+        }
+    };
+    if ptr.is_null() {
+        return Err(error::null_pointer("atomic operation on null pointer"));
+    }
+    let align = (bits / 8) as usize;
+    if (ptr as usize) & (align - 1) != 0 {
+        return Err(error::range(format!(
+            "atomic {} at address {:#x} is not {align}-byte aligned",
+            ty.name(),
+            ptr as usize
+        )));
+    }
+    Ok(AtomicSlot { ptr, bits, signed })
+}
+
+impl AtomicSlot {
+    fn encode(&self, value: &LuaValue) -> LuaResult<u64> {
+        if self.signed {
+            let value = types::to_signed(value, self.bits, Overflow::Error)?;
+            Ok(if self.bits == 32 {
+                u64::from(value as u32)
+            } else {
+                value as u64
+            })
+        } else {
+            types::to_unsigned(value, self.bits, Overflow::Error)
+        }
+    }
+
+    fn decode(&self, raw: u64) -> LuaResult<LuaValue> {
+        match (self.bits, self.signed) {
+            (32, true) => Ok(LuaValue::Integer(i64::from(raw as u32 as i32))),
+            (32, false) => Ok(LuaValue::Integer(raw as i64)),
+            (_, true) => Ok(LuaValue::Integer(raw as i64)),
+            (_, false) => types::u64_to_lua(raw),
+        }
+    }
+
+    fn load(&self, order: Ordering) -> u64 {
+        if self.bits == 32 {
+            u64::from(unsafe { AtomicU32::from_ptr(self.ptr as *mut u32) }.load(order))
+        } else {
+            unsafe { AtomicU64::from_ptr(self.ptr as *mut u64) }.load(order)
+        }
+    }
+
+    fn store(&self, value: u64, order: Ordering) {
+        if self.bits == 32 {
+            unsafe { AtomicU32::from_ptr(self.ptr as *mut u32) }.store(value as u32, order);
+        } else {
+            unsafe { AtomicU64::from_ptr(self.ptr as *mut u64) }.store(value, order);
+        }
+    }
+
+    // returns whether the exchange happened and the value that was in memory before it
+    fn compare_exchange(&self, expected: u64, desired: u64, order: Ordering) -> (bool, u64) {
+        let failure = match order {
+            Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+            Ordering::AcqRel | Ordering::Acquire => Ordering::Acquire,
+            _ => Ordering::SeqCst,
+        };
+        let result = if self.bits == 32 {
+            unsafe { AtomicU32::from_ptr(self.ptr as *mut u32) }
+                .compare_exchange(expected as u32, desired as u32, order, failure)
+                .map(u64::from)
+                .map_err(u64::from)
+        } else {
+            unsafe { AtomicU64::from_ptr(self.ptr as *mut u64) }
+                .compare_exchange(expected, desired, order, failure)
+        };
+        match result {
+            Ok(previous) => (true, previous),
+            Err(previous) => (false, previous),
+        }
+    }
+}
+
+pub fn register(lua: &Lua, exports: &LuaTable) -> LuaResult<()> {
+    let xor_fn = lua.create_function(|_, (ptr, key, count): (LuaLightUserData, u8, u64)| {
+        let bytes = region(ptr.0, count, "xor")?;
+        for byte in bytes {
+            *byte ^= key;
+        }
+        Ok(())
+    })?;
+    exports.set("xorMemory", xor_fn)?;
+
+    let read_bytes_fn = lua.create_function(|lua, (ptr, count): (LuaLightUserData, u64)| {
+        let bytes = region(ptr.0, count, "read bytes from")?;
+        lua.create_string(&*bytes)
+    })?;
+    exports.set("readBytes", read_bytes_fn)?;
+
+    let checksum_fn = lua.create_function(
+        |_, (ptr, count, mode): (LuaLightUserData, u64, Option<String>)| {
+            let bytes = region(ptr.0, count, "checksum")?;
+            match mode.as_deref().unwrap_or("crc32") {
+                "crc32" => Ok(crc32(bytes)),
+                "additive" | "sum" => Ok(additive(bytes)),
+                other => Err(error::type_error(format!(
+                    "unknown checksum mode '{other}' (expected 'crc32' or 'additive')"
+                ))),
+            }
+        },
+    )?;
+    exports.set("checksum", checksum_fn)?;
+
+    let load_bits_fn = lua.create_function(
+        |_, (ptr, offset, width, signed): (LuaLightUserData, u64, u32, Option<bool>)| {
+            let span = bit_span(offset, width)?;
+            let bytes = region(ptr.0, (span.first + span.len) as u64, "read bits from")?;
+            let raw = ((read_span(&bytes[span.first..]) & span.mask) >> span.shift) as u64;
+
+            if signed.unwrap_or(false) {
+                let shift = 64 - width;
+                return Ok(LuaValue::Integer(((raw << shift) as i64) >> shift));
+            }
+            types::u64_to_lua(raw)
+        },
+    )?;
+    exports.set("loadBits", load_bits_fn)?;
+
+    let store_bits_fn = lua.create_function(
+        |_, (ptr, offset, width, value): (LuaLightUserData, u64, u32, LuaValue)| {
+            let span = bit_span(offset, width)?;
+            let value = types::lua_value_to_i64(&value)?;
+            if width < 64 {
+                let min = -(1i128 << (width - 1));
+                let max = (1i128 << width) - 1;
+                if i128::from(value) < min || i128::from(value) > max {
+                    return Err(error::range(format!(
+                        "value {value} does not fit in {width} bit(s)"
+                    )));
+                }
+            }
+
+            let bytes = region(ptr.0, (span.first + span.len) as u64, "write bits to")?;
+            let target = &mut bytes[span.first..];
+            let current = read_span(target);
+            let bits = (u128::from(value as u64) << span.shift) & span.mask;
+            write_span(target, (current & !span.mask) | bits);
+            Ok(())
+        },
+    )?;
+    exports.set("storeBits", store_bits_fn)?;
+
+    let store_struct_bytes_fn =
+        lua.create_function(|_, (ptr, size, data): (LuaLightUserData, u64, LuaValue)| {
+            let LuaValue::Buffer(buffer) = &data else {
+                return Err(error::type_error(format!(
+                    "expected a buffer of struct bytes, got {}",
+                    data.type_name()
+                )));
+            };
+            let length = buffer.len() as u64;
+            if length != size {
+                return Err(error::range(format!(
+                    "buffer holds {length} byte(s) but the struct is {size} byte(s)"
+                )));
+            }
+
+            let bytes = region(ptr.0, size, "write struct bytes to")?;
+            // the buffer's storage is read in place; it stays alive through `data`
+            let source = data.to_pointer() as *const u8;
+            unsafe { std::ptr::copy_nonoverlapping(source, bytes.as_mut_ptr(), bytes.len()) };
+            Ok(())
+        })?;
+    exports.set("storeStructBytes", store_struct_bytes_fn)?;
+
+    let memory_fence_fn = lua.create_function(|_, order: Option<String>| {
+        atomic::fence(fence_ordering(order.as_deref())?);
+        Ok(())
+    })?;
+    exports.set("memoryFence", memory_fence_fn)?;
+
+    let compiler_fence_fn = lua.create_function(|_, order: Option<String>| {
+        atomic::compiler_fence(fence_ordering(order.as_deref())?);
+        Ok(())
+    })?;
+    exports.set("compilerFence", compiler_fence_fn)?;
+
+    let atomic_load_fn = lua.create_function(
+        |_, (ptr, code, order): (LuaLightUserData, String, Option<String>)| {
+            let slot = atomic_slot(ptr.0, &code)?;
+            let order = ordering(order.as_deref(), "an atomic load", &["release", "acqrel"])?;
+            slot.decode(slot.load(order))
+        },
+    )?;
+    exports.set("atomicLoad", atomic_load_fn)?;
+
+    let atomic_store_fn = lua.create_function(
+        |_, (ptr, code, value, order): (LuaLightUserData, String, LuaValue, Option<String>)| {
+            let slot = atomic_slot(ptr.0, &code)?;
+            let order = ordering(order.as_deref(), "an atomic store", &["acquire", "acqrel"])?;
+            slot.store(slot.encode(&value)?, order);
+            Ok(())
+        },
+    )?;
+    exports.set("atomicStore", atomic_store_fn)?;
+
+    let atomic_compare_exchange_fn = lua.create_function(
+        |_,
+         (ptr, code, expected, desired, order): (
+            LuaLightUserData,
+            String,
+            LuaValue,
+            LuaValue,
+            Option<String>,
+        )| {
+            let slot = atomic_slot(ptr.0, &code)?;
+            let order = ordering(order.as_deref(), "an atomic compare-exchange", &[])?;
+            let expected = slot.encode(&expected)?;
+            let desired = slot.encode(&desired)?;
+            let (exchanged, previous) = slot.compare_exchange(expected, desired, order);
+            Ok((exchanged, slot.decode(previous)?))
+        },
+    )?;
+    exports.set("atomicCompareExchange", atomic_compare_exchange_fn)?;
+
+    Ok(())
+}
@@ -1,13 +1,26 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
+use std::fs;
+use std::io::{self, Write};
 use std::os::raw::{c_char, c_int, c_void};
+use std::path::PathBuf;
 use std::ptr;
 use std::slice;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
 
 use mlua::prelude::*;
 
 use crate::call;
 use crate::callback;
-use crate::types::{self, TypeCode};
+use crate::cdecl;
+use crate::error;
+use crate::memory;
+use crate::signature;
+use crate::types::{self, Overflow, TypeCode};
+
+const CDATA_FACTORY_KEY: &str = "luneffi.cdata_factory";
 
 type TestCallback = unsafe extern "C" fn(c_int) -> c_int;
 
@@ -93,12 +106,12 @@ cfg_if::cfg_if! {
 }
 
 #[inline]
-fn get_errno() -> c_int {
+pub(crate) fn get_errno() -> c_int {
     unsafe { *errno_location() }
 }
 
 #[inline]
-fn set_errno(value: c_int) {
+pub(crate) fn set_errno(value: c_int) {
     unsafe {
         *errno_location() = value;
     }
@@ -107,9 +120,54 @@ fn set_errno(value: c_int) {
 #[allow(improper_ctypes)]
 unsafe extern "C" {
     fn luneffi_dlopen(path: *const c_char) -> *mut c_void;
+    fn luneffi_dlopen_flags(path: *const c_char, flags: c_int) -> *mut c_void;
+    fn luneffi_dlopen_existing(path: *const c_char) -> *mut c_void;
     fn luneffi_dlsym(handle: *mut c_void, name: *const c_char) -> *mut c_void;
     fn luneffi_dlclose(handle: *mut c_void) -> c_int;
     fn luneffi_dlerror() -> *const c_char;
+    fn luneffi_is_callable(ptr: *const c_void) -> c_int;
+    fn luneffi_exported_symbols(
+        handle: *mut c_void,
+        visit: SymbolVisitor,
+        context: *mut c_void,
+    ) -> c_int;
+}
+
+type SymbolVisitor = unsafe extern "C" fn(*const c_char, *mut c_void) -> c_int;
+
+unsafe extern "C" fn collect_symbol(name: *const c_char, context: *mut c_void) -> c_int {
+    let names = unsafe { &mut *(context as *mut Vec<String>) };
+    names.push(
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned(),
+    );
+    0
+}
+
+fn exported_symbols(handle: *mut c_void) -> LuaResult<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    let context = &mut names as *mut Vec<String> as *mut c_void;
+    if unsafe { luneffi_exported_symbols(handle, collect_symbol, context) } != 0 {
+        let reason = last_error().unwrap_or_else(|| "enumeration failed".to_string());
+        return Err(error::symbol(format!(
+            "cannot list the exports of library handle {handle:p}: {reason}"
+        )));
+    }
+    // versioned ELF symbols repeat a name once per version
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    Ok(names)
+}
+
+// best effort: Some(false) only for null pointers and addresses in non-executable
+// segments of loaded objects, None when the address is outside every known mapping
+pub(crate) fn is_callable(ptr: *const c_void) -> Option<bool> {
+    match unsafe { luneffi_is_callable(ptr) } {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
 }
 
 fn last_error() -> Option<String> {
@@ -121,6 +179,325 @@ fn last_error() -> Option<String> {
     Some(c_str.to_string_lossy().into_owned())
 }
 
+fn dlopen_failure(path: Option<&str>) -> String {
+    let target = path.map_or_else(|| "<process>".to_string(), |p| format!("'{p}'"));
+    match last_error() {
+        Some(reason) => format!("failed to load library {target}: {reason}"),
+        None => format!("failed to load library {target}"),
+    }
+}
+
+fn dlsym_failure(handle: *mut c_void, name: &str) -> String {
+    match last_error() {
+        Some(reason) => format!("symbol '{name}' not found in library handle {handle:p}: {reason}"),
+        None => format!("symbol '{name}' not found in library handle {handle:p}"),
+    }
+}
+
+// mirrors LUNEFFI_OPEN_* in luneffi_loader.h
+const OPEN_NOW: c_int = 1;
+const OPEN_GLOBAL: c_int = 2;
+
+fn open_flags(flags: Option<LuaTable>) -> LuaResult<c_int> {
+    let Some(flags) = flags else {
+        return Ok(0);
+    };
+    let mut bits = 0;
+    for pair in flags.pairs::<String, bool>() {
+        let (name, enabled) = pair?;
+        let bit = match name.as_str() {
+            "now" => OPEN_NOW,
+            "global" => OPEN_GLOBAL,
+            other => {
+                return Err(error::type_error(format!(
+                    "unknown library open flag '{other}' (expected 'now' or 'global')"
+                )));
+            }
+        };
+        if enabled {
+            bits |= bit;
+        }
+    }
+    Ok(bits)
+}
+
+fn close_handle(handle: *mut c_void, force: bool) -> LuaResult<()> {
+    let live = callback::live_count();
+    if live > 0 && !force {
+        return Err(error::load(format!(
+            "refusing to close library while {live} callback(s) are alive; pass force to close anyway"
+        )));
+    }
+
+    let rc = unsafe { luneffi_dlclose(handle) };
+    if rc != 0 {
+        let err = last_error().unwrap_or_else(|| "dlclose failed".to_string());
+        return Err(error::load(err));
+    }
+    release_memory_image(handle);
+    Ok(())
+}
+
+// a library whose handle belongs to the userdata, the way `CallbackHandle` owns its
+// closure; collecting the userdata closes the library
+struct LibraryHandle {
+    handle: Cell<*mut c_void>,
+}
+
+impl LibraryHandle {
+    fn open(path: &str, flags: c_int) -> LuaResult<Self> {
+        let c_path = CString::new(path)
+            .map_err(|_| error::type_error(format!("Library path contains NUL byte: {path}")))?;
+        let handle = unsafe { luneffi_dlopen_flags(c_path.as_ptr(), flags) };
+        if handle.is_null() {
+            return Err(error::load(dlopen_failure(Some(path))));
+        }
+        Ok(Self {
+            handle: Cell::new(handle),
+        })
+    }
+
+    // another reference to a module the process already has loaded, or None when it
+    // is not loaded; nothing new gets mapped either way
+    fn open_existing(name: &str) -> LuaResult<Option<Self>> {
+        let c_name = CString::new(name)
+            .map_err(|_| error::type_error(format!("Library name contains NUL byte: {name}")))?;
+        let handle = unsafe { luneffi_dlopen_existing(c_name.as_ptr()) };
+        if handle.is_null() {
+            return match last_error() {
+                Some(reason) => Err(error::load(format!(
+                    "failed to look up loaded library '{name}': {reason}"
+                ))),
+                None => Ok(None),
+            };
+        }
+        Ok(Some(Self {
+            handle: Cell::new(handle),
+        }))
+    }
+
+    fn get(&self) -> LuaResult<*mut c_void> {
+        let handle = self.handle.get();
+        if handle.is_null() {
+            return Err(error::load("library handle has been closed"));
+        }
+        Ok(handle)
+    }
+}
+
+impl Drop for LibraryHandle {
+    fn drop(&mut self) {
+        let handle = self.handle.replace(ptr::null_mut());
+        // like collected `ffi.load` libraries, stay loaded while callbacks are alive;
+        // nothing can report a failure from a finalizer
+        if !handle.is_null() && callback::live_count() == 0 {
+            unsafe { luneffi_dlclose(handle) };
+            release_memory_image(handle);
+        }
+    }
+}
+
+impl LuaUserData for LibraryHandle {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("handle", |_, this, ()| {
+            let handle = this.handle.get();
+            Ok((!handle.is_null()).then_some(LuaLightUserData(handle)))
+        });
+        methods.add_method("symbol", |_, this, name: String| {
+            let handle = this.get()?;
+            let c_name = CString::new(name.as_str())
+                .map_err(|_| error::type_error(format!("Symbol name contains NUL byte: {name}")))?;
+            let ptr = unsafe { luneffi_dlsym(handle, c_name.as_ptr()) };
+            if ptr.is_null() {
+                return Err(error::symbol(dlsym_failure(handle, &name)));
+            }
+            Ok(LuaLightUserData(ptr))
+        });
+        methods.add_method("close", |_, this, force: Option<bool>| {
+            let handle = this.handle.get();
+            if handle.is_null() {
+                return Ok(false);
+            }
+            close_handle(handle, force.unwrap_or(false))?;
+            this.handle.set(ptr::null_mut());
+            Ok(true)
+        });
+    }
+}
+
+// blocks from `alloc` that were not freed yet, address -> size; pointers that came from
+// anywhere else are left out of the counts when freed
+static LIVE_ALLOCATIONS: LazyLock<Mutex<HashMap<usize, usize>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static OUTSTANDING_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+static TOTAL_FREED: AtomicU64 = AtomicU64::new(0);
+
+fn live_allocations() -> MutexGuard<'static, HashMap<usize, usize>> {
+    LIVE_ALLOCATIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn track_alloc(ptr: *mut c_void, bytes: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    live_allocations().insert(ptr as usize, bytes);
+    OUTSTANDING_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    TOTAL_ALLOCATED.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+fn track_free(ptr: *mut c_void) {
+    if let Some(bytes) = live_allocations().remove(&(ptr as usize)) {
+        OUTSTANDING_ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+        TOTAL_FREED.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+const MEMORY_LIBRARY: &str = "<memory>";
+
+static MEMORY_IMAGES: Mutex<Vec<MemoryImage>> = Mutex::new(Vec::new());
+static NEXT_TEMP_LIBRARY: AtomicUsize = AtomicUsize::new(0);
+
+// backing storage of a library loaded from bytes, released when the handle is closed
+struct MemoryImage {
+    handle: usize,
+    // the memfd stays open so no later image can reuse its /proc/self/fd path while
+    // the loader still has a library registered under that name
+    _memfd: Option<fs::File>,
+    // Windows cannot delete a DLL while it is loaded, so its temporary copy lives
+    // until dlclose
+    temp_path: Option<PathBuf>,
+}
+
+impl Drop for MemoryImage {
+    fn drop(&mut self) {
+        if let Some(path) = self.temp_path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn keep_memory_image(image: MemoryImage) {
+    MEMORY_IMAGES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(image);
+}
+
+fn release_memory_image(handle: *mut c_void) {
+    let mut images = MEMORY_IMAGES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(index) = images
+        .iter()
+        .position(|image| image.handle == handle as usize)
+    {
+        images.swap_remove(index);
+    }
+}
+
+fn image_write_failure(err: io::Error) -> LuaError {
+    error::load(format!("failed to write library image: {err}"))
+}
+
+#[cfg(target_os = "linux")]
+fn dlopen_memfd(bytes: &[u8]) -> LuaResult<Option<*mut c_void>> {
+    use std::os::fd::FromRawFd;
+
+    let fd = unsafe { libc::memfd_create(c"luneffi".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        // kernels without memfd (or sandboxes that block it) use the temp-file path
+        return Ok(None);
+    }
+    let mut file = unsafe { fs::File::from_raw_fd(fd) };
+    file.write_all(bytes).map_err(image_write_failure)?;
+
+    let path = CString::new(format!("/proc/self/fd/{fd}")).expect("fd path has no NUL");
+    let handle = unsafe { luneffi_dlopen(path.as_ptr()) };
+    if handle.is_null() {
+        return Err(error::load(dlopen_failure(Some(MEMORY_LIBRARY))));
+    }
+    keep_memory_image(MemoryImage {
+        handle: handle as usize,
+        _memfd: Some(file),
+        temp_path: None,
+    });
+    Ok(Some(handle))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn dlopen_memfd(_bytes: &[u8]) -> LuaResult<Option<*mut c_void>> {
+    Ok(None)
+}
+
+fn create_temp_library() -> LuaResult<(PathBuf, fs::File)> {
+    let extension = if cfg!(windows) {
+        "dll"
+    } else if cfg!(target_os = "macos") {
+        "dylib"
+    } else {
+        "so"
+    };
+    loop {
+        let index = NEXT_TEMP_LIBRARY.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "luneffi-{}-{index}.{extension}",
+            std::process::id()
+        ));
+        // create_new never follows or reuses a file someone else placed there
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(error::load(format!(
+                    "failed to create temporary library file: {err}"
+                )));
+            }
+        }
+    }
+}
+
+fn dlopen_temp_file(bytes: &[u8]) -> LuaResult<*mut c_void> {
+    let (path, mut file) = create_temp_library()?;
+    let mut image = MemoryImage {
+        handle: 0,
+        _memfd: None,
+        temp_path: Some(path.clone()),
+    };
+    file.write_all(bytes).map_err(image_write_failure)?;
+    drop(file);
+
+    let c_path = CString::new(path.to_string_lossy().into_owned())
+        .map_err(|_| error::load("temporary library path contains NUL byte"))?;
+    let handle = unsafe { luneffi_dlopen(c_path.as_ptr()) };
+    if handle.is_null() {
+        return Err(error::load(dlopen_failure(Some(MEMORY_LIBRARY))));
+    }
+
+    // elsewhere the mapping survives unlinking, so the file goes away right now
+    if cfg!(windows) {
+        image.handle = handle as usize;
+        keep_memory_image(image);
+    }
+    Ok(handle)
+}
+
+fn dlopen_bytes(bytes: &[u8]) -> LuaResult<*mut c_void> {
+    if bytes.is_empty() {
+        return Err(error::type_error("library image is empty"));
+    }
+    match dlopen_memfd(bytes)? {
+        Some(handle) => Ok(handle),
+        None => dlopen_temp_file(bytes),
+    }
+}
+
 fn detect_os() -> &'static str {
     if cfg!(target_os = "windows") {
         "Windows"
@@ -196,6 +573,7 @@ fn build_abi_info(lua: &Lua) -> LuaResult<LuaTable> {
     table.set("softfp", softfp)?;
     table.set("hardfp", hardfp)?;
     table.set("win", cfg!(target_os = "windows"))?;
+    table.set("longdouble64", types::long_double_is_double())?;
     table.set(
         "bsd",
         cfg!(any(
@@ -224,6 +602,8 @@ fn build_primitive_layout(lua: &Lua) -> LuaResult<LuaTable> {
     let layout = lua.create_table()?;
     const CODES: &[&str] = &[
         "void",
+        "bool",
+        "char",
         "int8",
         "uint8",
         "int16",
@@ -234,6 +614,7 @@ fn build_primitive_layout(lua: &Lua) -> LuaResult<LuaTable> {
         "unsigned long",
         "long long",
         "unsigned long long",
+        "wchar_t",
         "size_t",
         "ssize_t",
         "intptr_t",
@@ -241,6 +622,7 @@ fn build_primitive_layout(lua: &Lua) -> LuaResult<LuaTable> {
         "ptrdiff_t",
         "float",
         "double",
+        "long double",
         "pointer",
     ];
 
@@ -255,7 +637,7 @@ fn build_primitive_layout(lua: &Lua) -> LuaResult<LuaTable> {
         entry.set(
             "size",
             i64::try_from(size).map_err(|_| {
-                LuaError::runtime(format!(
+                error::range(format!(
                     "primitive '{code}' size does not fit in Lua integer"
                 ))
             })?,
@@ -263,7 +645,7 @@ fn build_primitive_layout(lua: &Lua) -> LuaResult<LuaTable> {
         entry.set(
             "align",
             i64::try_from(align).map_err(|_| {
-                LuaError::runtime(format!(
+                error::range(format!(
                     "primitive '{code}' align does not fit in Lua integer"
                 ))
             })?,
@@ -280,114 +662,128 @@ fn lua_value_to_pointer(value: &LuaValue) -> LuaResult<*mut c_void> {
         LuaValue::LightUserData(ptr) => Ok(ptr.0),
         LuaValue::Integer(i) => {
             if *i < 0 {
-                return Err(LuaError::runtime(
-                    "pointer value must be non-negative".to_string(),
-                ));
+                return Err(error::range("pointer value must be non-negative"));
             }
             Ok((*i as u64) as usize as *mut c_void)
         }
         LuaValue::Number(n) => {
             if !n.is_finite() {
-                return Err(LuaError::runtime(
-                    "pointer value must be finite".to_string(),
-                ));
+                return Err(error::range("pointer value must be finite"));
             }
             if *n < 0.0 {
-                return Err(LuaError::runtime(
-                    "pointer value must be non-negative".to_string(),
-                ));
+                return Err(error::range("pointer value must be non-negative"));
             }
             if (n.trunc() - n).abs() > f64::EPSILON {
-                return Err(LuaError::runtime(
-                    "pointer value must be integral".to_string(),
-                ));
+                return Err(error::type_error("pointer value must be integral"));
             }
             Ok((*n as u64) as usize as *mut c_void)
         }
         LuaValue::Table(table) => {
             let marker = table.raw_get::<LuaValue>("__ffi_cdata")?;
             if !matches!(marker, LuaValue::Boolean(true)) {
-                return Err(LuaError::runtime(
-                    "cannot convert table value to native pointer".to_string(),
+                return Err(error::type_error(
+                    "cannot convert table value to native pointer",
                 ));
             }
             let inner = table.raw_get::<LuaValue>("__ptr")?;
-            match inner {
-                LuaValue::LightUserData(ptr) => Ok(ptr.0),
-                LuaValue::Nil => Ok(ptr::null_mut()),
-                other => Err(LuaError::runtime(format!(
-                    "cdata object missing native pointer (found {other:?})",
-                ))),
-            }
+            let base = match inner {
+                LuaValue::LightUserData(ptr) => ptr.0,
+                LuaValue::Nil => return Ok(ptr::null_mut()),
+                other => {
+                    return Err(error::type_error(format!(
+                        "cdata object missing native pointer (found {other:?})",
+                    )));
+                }
+            };
+            Ok(base.wrapping_byte_add(cdata_offset(table)?))
         }
-        other => Err(LuaError::runtime(format!(
+        other => Err(error::type_error(format!(
             "cannot convert value {other:?} to native pointer"
         ))),
     }
 }
 
-fn store_scalar(ptr: *mut c_void, ty: TypeCode, value: &LuaValue) -> LuaResult<()> {
+// pointer arithmetic on typed cdata accumulates a byte offset from the base pointer
+pub(crate) fn cdata_offset(table: &LuaTable) -> LuaResult<usize> {
+    match table.raw_get::<LuaValue>("__offset")? {
+        LuaValue::Nil => Ok(0),
+        LuaValue::Integer(offset) if offset >= 0 => Ok(offset as usize),
+        LuaValue::Number(offset) if offset >= 0.0 && offset.fract() == 0.0 => Ok(offset as usize),
+        other => Err(error::range(format!(
+            "cdata offset must be a non-negative integer (found {other:?})"
+        ))),
+    }
+}
+
+fn store_scalar(
+    ptr: *mut c_void,
+    ty: TypeCode,
+    value: &LuaValue,
+    overflow: Overflow,
+) -> LuaResult<()> {
     unsafe {
         match ty {
             TypeCode::Void => {
-                return Err(LuaError::runtime(
-                    "cannot store value for 'void' type".to_string(),
-                ));
+                return Err(error::type_error("cannot store value for 'void' type"));
             }
             TypeCode::Int8 => {
-                let v = types::clamp_signed(types::lua_value_to_i64(value)?, 8)? as i8;
-                ptr::write(ptr as *mut i8, v);
+                let v = types::to_signed(value, 8, overflow)? as i8;
+                ptr::write_unaligned(ptr as *mut i8, v);
+            }
+            TypeCode::Bool => {
+                let v = types::lua_value_to_bool(value)?;
+                ptr::write_unaligned(ptr as *mut u8, u8::from(v));
             }
             TypeCode::UInt8 => {
-                let v = types::clamp_unsigned(types::lua_value_to_u64(value)?, 8)? as u8;
-                ptr::write(ptr as *mut u8, v);
+                let v = types::to_unsigned(value, 8, overflow)? as u8;
+                ptr::write_unaligned(ptr as *mut u8, v);
             }
             TypeCode::Int16 => {
-                let v = types::clamp_signed(types::lua_value_to_i64(value)?, 16)? as i16;
-                ptr::write(ptr as *mut i16, v);
+                let v = types::to_signed(value, 16, overflow)? as i16;
+                ptr::write_unaligned(ptr as *mut i16, v);
             }
             TypeCode::UInt16 => {
-                let v = types::clamp_unsigned(types::lua_value_to_u64(value)?, 16)? as u16;
-                ptr::write(ptr as *mut u16, v);
+                let v = types::to_unsigned(value, 16, overflow)? as u16;
+                ptr::write_unaligned(ptr as *mut u16, v);
             }
             TypeCode::Int32 => {
-                let v = types::clamp_signed(types::lua_value_to_i64(value)?, 32)? as i32;
-                ptr::write(ptr as *mut i32, v);
+                let v = types::to_signed(value, 32, overflow)? as i32;
+                ptr::write_unaligned(ptr as *mut i32, v);
             }
             TypeCode::UInt32 => {
-                let v = types::clamp_unsigned(types::lua_value_to_u64(value)?, 32)? as u32;
-                ptr::write(ptr as *mut u32, v);
+                let v = types::to_unsigned(value, 32, overflow)? as u32;
+                ptr::write_unaligned(ptr as *mut u32, v);
             }
             TypeCode::Int64 => {
-                let v = types::lua_value_to_i64(value)?;
-                ptr::write(ptr as *mut i64, v);
+                let v = types::to_signed(value, 64, overflow)?;
+                ptr::write_unaligned(ptr as *mut i64, v);
             }
             TypeCode::UInt64 => {
-                let v = types::lua_value_to_u64(value)?;
-                ptr::write(ptr as *mut u64, v);
+                let v = types::to_unsigned(value, 64, overflow)?;
+                ptr::write_unaligned(ptr as *mut u64, v);
             }
             TypeCode::IntPtr => {
                 let bits = usize::BITS;
-                let value = types::clamp_signed(types::lua_value_to_i64(value)?, bits)?;
+                let value = types::to_signed(value, bits, overflow)?;
                 if bits == 64 {
-                    ptr::write(ptr as *mut i64, value);
+                    ptr::write_unaligned(ptr as *mut i64, value);
                 } else {
-                    ptr::write(ptr as *mut i32, value as i32);
+                    ptr::write_unaligned(ptr as *mut i32, value as i32);
                 }
             }
             TypeCode::UIntPtr => {
                 let bits = usize::BITS;
-                let value = types::clamp_unsigned(types::lua_value_to_u64(value)?, bits)?;
+                let value = types::to_unsigned(value, bits, overflow)?;
                 if bits == 64 {
-                    ptr::write(ptr as *mut u64, value);
+                    ptr::write_unaligned(ptr as *mut u64, value);
                 } else {
-                    ptr::write(ptr as *mut u32, value as u32);
+                    ptr::write_unaligned(ptr as *mut u32, value as u32);
                 }
             }
             TypeCode::Float32 => {
                 let v = match value {
                     LuaValue::Number(n) => *n as f32,
-                    LuaValue::Integer(i) => *i as f32,
+                    LuaValue::Integer(i) => types::integer_to_f32(*i)?,
                     LuaValue::Boolean(b) => {
                         if *b {
                             1.0
@@ -396,17 +792,17 @@ fn store_scalar(ptr: *mut c_void, ty: TypeCode, value: &LuaValue) -> LuaResult<(
                         }
                     }
                     other => {
-                        return Err(LuaError::runtime(format!(
+                        return Err(error::type_error(format!(
                             "expected numeric value for float storage, got {other:?}"
                         )));
                     }
                 };
-                ptr::write(ptr as *mut f32, v);
+                ptr::write_unaligned(ptr as *mut f32, v);
             }
             TypeCode::Float64 => {
                 let v = match value {
                     LuaValue::Number(n) => *n,
-                    LuaValue::Integer(i) => *i as f64,
+                    LuaValue::Integer(i) => types::integer_to_f64(*i)?,
                     LuaValue::Boolean(b) => {
                         if *b {
                             1.0
@@ -415,16 +811,33 @@ fn store_scalar(ptr: *mut c_void, ty: TypeCode, value: &LuaValue) -> LuaResult<(
                         }
                     }
                     other => {
-                        return Err(LuaError::runtime(format!(
+                        return Err(error::type_error(format!(
                             "expected numeric value for double storage, got {other:?}"
                         )));
                     }
                 };
-                ptr::write(ptr as *mut f64, v);
+                ptr::write_unaligned(ptr as *mut f64, v);
             }
             TypeCode::Pointer => {
                 let p = lua_value_to_pointer(value)?;
-                ptr::write(ptr as *mut *mut c_void, p);
+                ptr::write_unaligned(ptr as *mut *mut c_void, p);
+            }
+            TypeCode::LongDouble => {
+                let v = match value {
+                    LuaValue::Number(n) => *n,
+                    LuaValue::Integer(i) => types::integer_to_f64(*i)?,
+                    other => {
+                        return Err(error::type_error(format!(
+                            "expected numeric value for long double storage, got {other:?}"
+                        )));
+                    }
+                };
+                types::write_long_double(ptr as *mut u8, v)?;
+            }
+            TypeCode::Struct => {
+                return Err(error::type_error(
+                    "cannot store aggregate value as a scalar",
+                ));
             }
         }
     }
@@ -432,73 +845,537 @@ fn store_scalar(ptr: *mut c_void, ty: TypeCode, value: &LuaValue) -> LuaResult<(
     Ok(())
 }
 
+fn normalize_descriptor(lua: &Lua, value: LuaValue) -> LuaResult<LuaTable> {
+    let normalized = lua.create_table()?;
+    let descriptor = match value {
+        LuaValue::String(code) => {
+            normalized.set("kind", "primitive")?;
+            normalized.set("code", types::normalize_code(code.to_str()?.as_ref()))?;
+            return Ok(normalized);
+        }
+        LuaValue::Table(descriptor) => descriptor,
+        other => {
+            return Err(error::type_error(format!(
+                "cdata object has invalid __ctype field (found {other:?})"
+            )));
+        }
+    };
+
+    let code = descriptor.raw_get::<Option<String>>("code")?;
+    normalized.set("kind", descriptor.raw_get::<Option<String>>("kind")?)?;
+    normalized.set("code", code.as_deref().map(types::normalize_code))?;
+    normalized.set("name", descriptor.raw_get::<Option<String>>("name")?)?;
+    normalized.set("size", descriptor.raw_get::<Option<usize>>("size")?)?;
+    normalized.set("align", descriptor.raw_get::<Option<usize>>("align")?)?;
+
+    // pointees are only named, so self-referential records do not recurse forever
+    if let Some(base) = descriptor.raw_get::<Option<LuaTable>>("base")? {
+        normalized.set("baseName", base.raw_get::<Option<String>>("name")?)?;
+    }
+
+    if let Some(fields) = descriptor.raw_get::<Option<LuaTable>>("fields")? {
+        let list = lua.create_table()?;
+        for field in fields.sequence_values::<LuaTable>() {
+            let field = field?;
+            let entry = lua.create_table()?;
+            entry.set("name", field.raw_get::<LuaValue>("name")?)?;
+            entry.set("offset", field.raw_get::<LuaValue>("offset")?)?;
+            entry.set("bitWidth", field.raw_get::<LuaValue>("bitWidth")?)?;
+            entry.set("bitOffset", field.raw_get::<LuaValue>("bitOffset")?)?;
+            entry.set(
+                "type",
+                normalize_descriptor(lua, field.raw_get::<LuaValue>("ctype")?)?,
+            )?;
+            list.raw_push(entry)?;
+        }
+        normalized.set("fields", list)?;
+    }
+
+    Ok(normalized)
+}
+
+fn type_of(lua: &Lua, value: LuaValue) -> LuaResult<Option<LuaTable>> {
+    let LuaValue::Table(table) = value else {
+        return Ok(None);
+    };
+    if call::extract_cdata_info(&table)?.is_none() {
+        return Ok(None);
+    }
+    match table.raw_get::<LuaValue>("__ctype")? {
+        LuaValue::Nil => Ok(None),
+        descriptor => normalize_descriptor(lua, descriptor).map(Some),
+    }
+}
+
+const DESCRIBE_PREVIEW_BYTES: usize = 16;
+
+fn describe_cdata(table: &LuaTable) -> LuaResult<String> {
+    let info = call::extract_cdata_info(table)?
+        .ok_or_else(|| error::type_error("describe expects a cdata value"))?;
+
+    let (name, described_size) = match table.raw_get::<LuaValue>("__ctype")? {
+        LuaValue::Table(descriptor) => {
+            let name = match descriptor.raw_get::<Option<String>>("name")? {
+                Some(name) => Some(name),
+                None => descriptor.raw_get::<Option<String>>("code")?,
+            };
+            // pointer cdata hold the address itself, so the preview shows the pointee
+            let size =
+                if descriptor.raw_get::<Option<String>>("kind")?.as_deref() == Some("pointer") {
+                    match descriptor.raw_get::<Option<LuaTable>>("base")? {
+                        Some(base) => Some(base.raw_get::<Option<usize>>("size")?.unwrap_or(0)),
+                        None => Some(0),
+                    }
+                } else {
+                    descriptor.raw_get::<Option<usize>>("size")?
+                };
+            (name, size)
+        }
+        LuaValue::String(code) => (Some(code.to_str()?.to_string()), None),
+        _ => (None, None),
+    };
+    let name = name.unwrap_or_else(|| "<unknown>".to_string());
+
+    let ptr = match info.ptr {
+        Some(ptr) if !ptr.is_null() => ptr,
+        _ => return Ok(format!("cdata<{name}>: NULL")),
+    };
+
+    let size = described_size
+        .or_else(|| info.type_code.map(TypeCode::size_of))
+        .unwrap_or(0);
+    let preview_len = size.min(DESCRIBE_PREVIEW_BYTES);
+    let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, preview_len) };
+    let mut hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if size > preview_len {
+        hex.push_str(" ...");
+    }
+
+    Ok(format!("cdata<{name}>: {ptr:p} ({size} bytes) [{hex}]"))
+}
+
+fn load_symbol_value(lua: &Lua, ptr: *mut c_void, code: &str) -> LuaResult<LuaValue> {
+    let normalized = types::normalize_code(code).replace(' ', "");
+    match normalized.as_str() {
+        "char*" | "string" => {
+            let value = unsafe { ptr::read_unaligned(ptr as *const *const c_char) };
+            if value.is_null() {
+                return Ok(LuaValue::Nil);
+            }
+            let bytes = unsafe { CStr::from_ptr(value).to_bytes() };
+            Ok(LuaValue::String(lua.create_string(bytes)?))
+        }
+        _ => load_scalar(lua, ptr, TypeCode::from_code(&types::normalize_code(code))?),
+    }
+}
+
 fn load_scalar(_lua: &Lua, ptr: *mut c_void, ty: TypeCode) -> LuaResult<LuaValue> {
     unsafe {
         match ty {
-            TypeCode::Void => Err(LuaError::runtime(
-                "cannot read value of 'void' type".to_string(),
+            TypeCode::Void => Err(error::type_error("cannot read value of 'void' type")),
+            TypeCode::Bool => Ok(LuaValue::Boolean(
+                ptr::read_unaligned(ptr as *const u8) != 0,
+            )),
+            TypeCode::Int8 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const i8) as i64
+            )),
+            TypeCode::UInt8 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const u8) as i64
             )),
-            TypeCode::Int8 => Ok(LuaValue::Integer(ptr::read(ptr as *const i8) as i64)),
-            TypeCode::UInt8 => Ok(LuaValue::Integer(ptr::read(ptr as *const u8) as i64)),
-            TypeCode::Int16 => Ok(LuaValue::Integer(ptr::read(ptr as *const i16) as i64)),
-            TypeCode::UInt16 => Ok(LuaValue::Integer(ptr::read(ptr as *const u16) as i64)),
-            TypeCode::Int32 => Ok(LuaValue::Integer(ptr::read(ptr as *const i32) as i64)),
-            TypeCode::UInt32 => Ok(LuaValue::Integer(ptr::read(ptr as *const u32) as i64)),
-            TypeCode::Int64 => Ok(LuaValue::Integer(ptr::read(ptr as *const i64))),
+            TypeCode::Int16 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const i16) as i64
+            )),
+            TypeCode::UInt16 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const u16) as i64
+            )),
+            TypeCode::Int32 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const i32) as i64
+            )),
+            TypeCode::UInt32 => Ok(LuaValue::Integer(
+                ptr::read_unaligned(ptr as *const u32) as i64
+            )),
+            TypeCode::Int64 => Ok(LuaValue::Integer(ptr::read_unaligned(ptr as *const i64))),
             TypeCode::UInt64 => {
-                let value = ptr::read(ptr as *const u64);
-                if value <= i64::MAX as u64 {
-                    Ok(LuaValue::Integer(value as i64))
-                } else {
-                    Ok(LuaValue::Number(value as f64))
-                }
+                let value = ptr::read_unaligned(ptr as *const u64);
+                types::u64_to_lua(value)
             }
             TypeCode::IntPtr => {
                 if usize::BITS == 64 {
-                    Ok(LuaValue::Integer(ptr::read(ptr as *const i64)))
+                    Ok(LuaValue::Integer(ptr::read_unaligned(ptr as *const i64)))
                 } else {
-                    Ok(LuaValue::Integer(ptr::read(ptr as *const i32) as i64))
+                    Ok(LuaValue::Integer(
+                        ptr::read_unaligned(ptr as *const i32) as i64
+                    ))
                 }
             }
             TypeCode::UIntPtr => {
                 if usize::BITS == 64 {
-                    let value = ptr::read(ptr as *const u64);
-                    if value <= i64::MAX as u64 {
-                        Ok(LuaValue::Integer(value as i64))
-                    } else {
-                        Ok(LuaValue::Number(value as f64))
-                    }
+                    let value = ptr::read_unaligned(ptr as *const u64);
+                    types::u64_to_lua(value)
                 } else {
-                    Ok(LuaValue::Integer(ptr::read(ptr as *const u32) as i64))
+                    Ok(LuaValue::Integer(
+                        ptr::read_unaligned(ptr as *const u32) as i64
+                    ))
                 }
             }
-            TypeCode::Float32 => Ok(LuaValue::Number(ptr::read(ptr as *const f32) as f64)),
-            TypeCode::Float64 => Ok(LuaValue::Number(ptr::read(ptr as *const f64))),
+            TypeCode::Float32 => Ok(LuaValue::Number(
+                ptr::read_unaligned(ptr as *const f32) as f64
+            )),
+            TypeCode::Float64 => Ok(LuaValue::Number(ptr::read_unaligned(ptr as *const f64))),
             TypeCode::Pointer => {
-                let value = ptr::read(ptr as *const *mut c_void);
+                let value = ptr::read_unaligned(ptr as *const *mut c_void);
                 Ok(LuaValue::LightUserData(LuaLightUserData(value)))
             }
+            TypeCode::LongDouble => {
+                Ok(LuaValue::Number(types::read_long_double(ptr as *const u8)?))
+            }
+            TypeCode::Struct => Err(error::type_error("cannot read aggregate value as a scalar")),
+        }
+    }
+}
+
+pub(crate) fn make_cdata(
+    lua: &Lua,
+    descriptor: impl IntoLua,
+    ptr: *mut c_void,
+    owned: bool,
+) -> LuaResult<LuaValue> {
+    if let Ok(factory) = lua.named_registry_value::<LuaFunction>(CDATA_FACTORY_KEY) {
+        return factory.call::<LuaValue>((descriptor, LuaLightUserData(ptr), owned));
+    }
+
+    let table = lua.create_table()?;
+    table.raw_set("__ffi_cdata", true)?;
+    table.raw_set("__ctype", descriptor)?;
+    table.raw_set("__ptr", LuaLightUserData(ptr))?;
+    table.raw_set("__owned", owned)?;
+    Ok(LuaValue::Table(table))
+}
+
+pub(crate) fn box_integer(lua: &Lua, descriptor: &str, bits: u64) -> LuaResult<LuaValue> {
+    let storage = unsafe { calloc(1, std::mem::size_of::<u64>() as size_t) };
+    if storage.is_null() {
+        return Err(error::range("failed to allocate 8 byte(s)"));
+    }
+    unsafe { ptr::write_unaligned(storage as *mut u64, bits) };
+    make_cdata(lua, descriptor, storage, true)
+}
+
+// copies a pointer-width integer into an `intptr_t`/`uintptr_t` cdata box, so loads look
+// the same on 32- and 64-bit targets
+fn box_pointer_width(lua: &Lua, ptr: *mut c_void, ty: TypeCode) -> LuaResult<LuaValue> {
+    let size = std::mem::size_of::<usize>();
+    let storage = unsafe { calloc(1, size as size_t) };
+    if storage.is_null() {
+        return Err(error::range(format!("failed to allocate {size} byte(s)")));
+    }
+    unsafe { ptr::copy_nonoverlapping(ptr as *const u8, storage as *mut u8, size) };
+    let descriptor = if ty == TypeCode::IntPtr {
+        "intptr_t"
+    } else {
+        "uintptr_t"
+    };
+    make_cdata(lua, descriptor, storage, true)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct LoadOptions {
+    box_unsigned: bool,
+    as_string: bool,
+    box_pointer_width: bool,
+    signaling: bool,
+}
+
+impl LoadOptions {
+    fn from_table(table: Option<LuaTable>) -> LuaResult<Self> {
+        let Some(table) = table else {
+            return Ok(Self::default());
+        };
+        Ok(Self {
+            box_unsigned: table.get::<Option<bool>>("boxUnsigned")?.unwrap_or(false),
+            as_string: table.get::<Option<bool>>("asString")?.unwrap_or(false),
+            box_pointer_width: table
+                .get::<Option<bool>>("boxPointerWidth")?
+                .unwrap_or(false),
+            signaling: table.get::<Option<bool>>("signaling")?.unwrap_or(false),
+        })
+    }
+}
+
+fn is_wide_signed(ty: TypeCode) -> bool {
+    matches!(ty, TypeCode::Int64) || (ty == TypeCode::IntPtr && cfg!(target_pointer_width = "64"))
+}
+
+fn is_wide_unsigned(ty: TypeCode) -> bool {
+    matches!(ty, TypeCode::UInt64) || (ty == TypeCode::UIntPtr && cfg!(target_pointer_width = "64"))
+}
+
+fn scalar_type(lua: &Lua, spec: LuaValue) -> LuaResult<TypeCode> {
+    let code = match spec {
+        LuaValue::Table(descriptor) => descriptor
+            .get::<String>("code")
+            .map_err(|_| error::type_error("Type descriptor missing 'code' field"))?,
+        other => String::from_lua(other, lua)?,
+    };
+    TypeCode::from_code(&types::normalize_code(&code))
+}
+
+fn store_overflow(options: Option<LuaTable>) -> LuaResult<Overflow> {
+    match options {
+        Some(options) if options.get::<Option<bool>>("wrap")?.unwrap_or(false) => {
+            Ok(Overflow::Wrap)
         }
+        _ => Ok(Overflow::Error),
     }
 }
 
+// the address of a scalar slot inside a Luau buffer, after checking it lies entirely
+// within the buffer's bytes
+fn buffer_slot(target: &LuaValue, offset: &LuaValue, ty: TypeCode) -> LuaResult<*mut c_void> {
+    let LuaValue::Buffer(buffer) = target else {
+        return Err(error::type_error(format!(
+            "expected a buffer, got {}",
+            target.type_name()
+        )));
+    };
+    if matches!(ty, TypeCode::Void | TypeCode::Struct) {
+        return Err(error::type_error(format!(
+            "buffer access needs a scalar type, got '{}'",
+            ty.name()
+        )));
+    }
+    let offset = types::lua_value_to_u64(offset)?;
+    let size = ty.size_of() as u64;
+    let len = buffer.len() as u64;
+    if offset.checked_add(size).is_none_or(|end| end > len) {
+        return Err(error::range(format!(
+            "{size}-byte {} at offset {offset} does not fit a {len}-byte buffer",
+            ty.name()
+        )));
+    }
+    // the buffer's storage stays alive through `target` for the caller's access
+    Ok(unsafe { (target.to_pointer() as *mut u8).add(offset as usize) } as *mut c_void)
+}
+
+// a signaling NaN has an all-ones exponent, a nonzero fraction, and the quiet bit clear;
+// poison fills use the pattern to mark memory that was never written
+fn reject_signaling_nan(ptr: *mut c_void, ty: TypeCode) -> LuaResult<()> {
+    let bits = match ty {
+        TypeCode::Float32 => {
+            let bits = unsafe { ptr::read_unaligned(ptr as *const u32) };
+            let signaling = bits & 0x7f80_0000 == 0x7f80_0000
+                && bits & 0x0040_0000 == 0
+                && bits & 0x003f_ffff != 0;
+            signaling.then_some(u64::from(bits))
+        }
+        TypeCode::Float64 => {
+            let bits = unsafe { ptr::read_unaligned(ptr as *const u64) };
+            let signaling = bits & 0x7ff0_0000_0000_0000 == 0x7ff0_0000_0000_0000
+                && bits & 0x0008_0000_0000_0000 == 0
+                && bits & 0x0007_ffff_ffff_ffff != 0;
+            signaling.then_some(bits)
+        }
+        _ => None,
+    };
+    match bits {
+        Some(bits) => Err(error::range(format!(
+            "{} at {ptr:p} holds a signaling NaN (bits {bits:#x}); the memory was likely never written",
+            ty.name()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn load_scalar_with(
+    lua: &Lua,
+    ptr: *mut c_void,
+    ty: TypeCode,
+    options: LoadOptions,
+) -> LuaResult<LuaValue> {
+    if options.signaling {
+        reject_signaling_nan(ptr, ty)?;
+    }
+
+    if options.box_pointer_width && matches!(ty, TypeCode::IntPtr | TypeCode::UIntPtr) {
+        return box_pointer_width(lua, ptr, ty);
+    }
+
+    if options.as_string && is_wide_signed(ty) {
+        let value = unsafe { ptr::read_unaligned(ptr as *const i64) };
+        return Ok(LuaValue::String(lua.create_string(value.to_string())?));
+    }
+
+    if is_wide_unsigned(ty) && (options.as_string || options.box_unsigned) {
+        let value = unsafe { ptr::read_unaligned(ptr as *const u64) };
+        if options.as_string {
+            return Ok(LuaValue::String(lua.create_string(value.to_string())?));
+        }
+        if value > i64::MAX as u64 {
+            // a fresh unsigned long long cdata keeps the exact bits instead of rounding through f64
+            return box_integer(lua, "unsigned long long", value);
+        }
+    }
+
+    load_scalar(lua, ptr, ty)
+}
+
+fn load_described(
+    lua: &Lua,
+    ptr: *mut c_void,
+    descriptor: LuaTable,
+    mut options: LoadOptions,
+) -> LuaResult<LuaValue> {
+    if descriptor
+        .get::<Option<bool>>("boxPointerWidth")?
+        .unwrap_or(false)
+    {
+        options.box_pointer_width = true;
+    }
+
+    // function pointers come back bare, or nil when null; the Luau side binds them to
+    // their signature
+    if descriptor.get::<Option<String>>("kind")?.as_deref() == Some("fnptr") {
+        let value = unsafe { ptr::read_unaligned(ptr as *const *mut c_void) };
+        if value.is_null() {
+            return Ok(LuaValue::Nil);
+        }
+        return Ok(LuaValue::LightUserData(LuaLightUserData(value)));
+    }
+
+    let code: String = descriptor
+        .get("code")
+        .map_err(|_| error::type_error("Type descriptor missing 'code' field"))?;
+    let normalized = types::normalize_code(&code);
+    let ty = TypeCode::from_code(&normalized)?;
+
+    let has_pointee = !matches!(descriptor.get::<LuaValue>("base")?, LuaValue::Nil)
+        || !matches!(descriptor.get::<LuaValue>("pointee")?, LuaValue::Nil);
+    if ty != TypeCode::Pointer || !has_pointee {
+        return load_scalar_with(lua, ptr, ty, options);
+    }
+
+    let value = unsafe { ptr::read_unaligned(ptr as *const *mut c_void) };
+    if value.is_null() {
+        return Ok(LuaValue::Nil);
+    }
+    make_cdata(lua, descriptor, value, false)
+}
+
+fn byte_offset(ptr: *mut c_void, offset: Option<u64>) -> LuaResult<*mut c_void> {
+    let offset = usize::try_from(offset.unwrap_or(0))
+        .map_err(|_| error::range("byte offset does not fit usize"))?;
+    Ok((ptr as *mut u8).wrapping_add(offset) as *mut c_void)
+}
+
+fn read_string(lua: &Lua, ptr: *mut c_void, len: Option<u64>) -> LuaResult<LuaValue> {
+    let bytes = match len {
+        Some(count) => {
+            let count = usize::try_from(count)
+                .map_err(|_| error::range("string length does not fit usize"))?;
+            unsafe { slice::from_raw_parts(ptr as *const u8, count) }
+        }
+        None => unsafe { CStr::from_ptr(ptr as *const c_char).to_bytes() },
+    };
+
+    let lua_string = lua.create_string(bytes)?;
+    Ok(LuaValue::String(lua_string))
+}
+
+// `len` counts code units: bytes for the 8-bit encodings and 16-bit units for UTF-16;
+// without it the text runs up to the first zero unit
+fn read_string_encoded(
+    lua: &Lua,
+    ptr: *mut c_void,
+    encoding: &str,
+    len: Option<u64>,
+) -> LuaResult<LuaValue> {
+    let len = len
+        .map(|count| {
+            usize::try_from(count).map_err(|_| error::range("string length does not fit usize"))
+        })
+        .transpose()?;
+
+    let text = match encoding {
+        "utf8" | "latin1" => {
+            let bytes = match len {
+                Some(count) => unsafe { slice::from_raw_parts(ptr as *const u8, count) },
+                None => unsafe { CStr::from_ptr(ptr as *const c_char).to_bytes() },
+            };
+            if encoding == "utf8" {
+                String::from_utf8_lossy(bytes).into_owned()
+            } else {
+                bytes.iter().map(|&byte| char::from(byte)).collect()
+            }
+        }
+        "utf16le" | "utf16be" => {
+            let units = ptr as *const [u8; 2];
+            let count = len.unwrap_or_else(|| {
+                let mut count = 0;
+                while unsafe { ptr::read_unaligned(units.add(count)) } != [0, 0] {
+                    count += 1;
+                }
+                count
+            });
+            let decode: fn([u8; 2]) -> u16 = if encoding == "utf16le" {
+                u16::from_le_bytes
+            } else {
+                u16::from_be_bytes
+            };
+            let units =
+                (0..count).map(|index| decode(unsafe { ptr::read_unaligned(units.add(index)) }));
+            char::decode_utf16(units)
+                .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        "utf32le" | "utf32be" => {
+            let units = ptr as *const [u8; 4];
+            let count = len.unwrap_or_else(|| {
+                let mut count = 0;
+                while unsafe { ptr::read_unaligned(units.add(count)) } != [0; 4] {
+                    count += 1;
+                }
+                count
+            });
+            let decode: fn([u8; 4]) -> u32 = if encoding == "utf32le" {
+                u32::from_le_bytes
+            } else {
+                u32::from_be_bytes
+            };
+            // surrogates and values past U+10FFFF are not scalar values
+            (0..count)
+                .map(|index| decode(unsafe { ptr::read_unaligned(units.add(index)) }))
+                .map(|unit| char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        other => {
+            return Err(error::type_error(format!(
+                "unknown string encoding '{other}' (expected 'utf8', 'latin1', 'utf16le', 'utf16be', 'utf32le', or 'utf32be')"
+            )));
+        }
+    };
+
+    Ok(LuaValue::String(lua.create_string(text)?))
+}
+
 pub fn create(lua: &Lua) -> LuaResult<LuaTable> {
     let table = lua.create_table()?;
 
     let pointer_size = std::mem::size_of::<*mut c_void>();
     table.set(
         "pointerSize",
-        i64::try_from(pointer_size).map_err(|_| {
-            LuaError::runtime("pointer size does not fit in Lua integer".to_string())
-        })?,
+        i64::try_from(pointer_size)
+            .map_err(|_| error::range("pointer size does not fit in Lua integer"))?,
     )?;
 
     let pointer_align = std::mem::align_of::<*mut c_void>();
     table.set(
         "pointerAlign",
-        i64::try_from(pointer_align).map_err(|_| {
-            LuaError::runtime("pointer alignment does not fit in Lua integer".to_string())
-        })?,
+        i64::try_from(pointer_align)
+            .map_err(|_| error::range("pointer alignment does not fit in Lua integer"))?,
     )?;
 
     let primitive_layout = build_primitive_layout(lua)?;
@@ -511,13 +1388,79 @@ pub fn create(lua: &Lua) -> LuaResult<LuaTable> {
     table.set("platformArch", arch_string)?;
 
     let abi_info = build_abi_info(lua)?;
-    table.set("abiInfo", abi_info)?;
+    table.set("abiInfo", abi_info.clone())?;
+
+    let platform_info = lua.create_table()?;
+    platform_info.set("os", detect_os())?;
+    platform_info.set("arch", detect_arch())?;
+    platform_info.set("pointerSize", pointer_size)?;
+    platform_info.set("charSigned", types::CHAR_SIGNED)?;
+    table.set("platformInfo", platform_info)?;
+
+    let resolve_type_fn = lua.create_function(|lua, code: String| {
+        let ty = TypeCode::from_code(&types::normalize_code(&code))?;
+        let info = lua.create_table()?;
+        info.set("name", ty.name())?;
+        info.set("size", ty.size_of())?;
+        info.set("align", ty.align_of())?;
+        if types::is_const_qualified(&code) {
+            info.set("const", true)?;
+        }
+        Ok(info)
+    })?;
+    table.set("resolveType", resolve_type_fn)?;
+
+    let register_alias_fn = lua.create_function(|lua, (name, target): (String, String)| {
+        let ty = types::register_alias(&name, &target)?;
+        let info = lua.create_table()?;
+        info.set("name", ty.name())?;
+        info.set("size", ty.size_of())?;
+        info.set("align", ty.align_of())?;
+        Ok(info)
+    })?;
+    table.set("registerAlias", register_alias_fn)?;
+
+    let array_size_fn = lua.create_function(|_, (element, count): (LuaValue, LuaValue)| {
+        // registry descriptors carry their size; anything else is laid out here
+        let declared = match &element {
+            LuaValue::Table(descriptor) => descriptor.raw_get::<Option<u64>>("size")?,
+            _ => None,
+        };
+        let size = match declared {
+            Some(size) => size,
+            None => signature::CType::from_lua(element)?.size() as u64,
+        };
+        if size == 0 {
+            return Err(error::type_error(
+                "array elements must have a non-zero size",
+            ));
+        }
+        let count = types::lua_value_to_u64(&count)?;
+        size.checked_mul(count)
+            .and_then(|total| usize::try_from(total).ok())
+            .ok_or_else(|| {
+                error::range(format!(
+                    "array of {count} element(s) of {size} byte(s) does not fit usize"
+                ))
+            })
+    })?;
+    table.set("arraySize", array_size_fn)?;
+
+    let abi_fn = lua.create_function(move |_, param: String| {
+        match abi_info.raw_get::<Option<bool>>(param.as_str())? {
+            Some(value) => Ok(value),
+            None => Err(error::type_error(format!(
+                "unknown ABI attribute '{param}'"
+            ))),
+        }
+    })?;
+    table.set("abi", abi_fn)?;
 
     let dlopen_fn = lua.create_function(|_, path: Option<String>| {
         let c_path =
             match path {
                 Some(ref p) => Some(CString::new(p.as_str()).map_err(|_| {
-                    LuaError::runtime(format!("Library path contains NUL byte: {p}"))
+                    error::type_error(format!("Library path contains NUL byte: {p}"))
                 })?),
                 None => None,
             };
@@ -526,21 +1469,25 @@ pub fn create(lua: &Lua) -> LuaResult<LuaTable> {
             unsafe { luneffi_dlopen(c_path.as_ref().map_or(std::ptr::null(), |s| s.as_ptr())) };
 
         if ptr.is_null() {
-            let err = last_error().unwrap_or_else(|| "Failed to load library".to_string());
-            return Err(LuaError::runtime(err));
+            return Err(error::load(dlopen_failure(path.as_deref())));
         }
 
         Ok(LuaLightUserData(ptr))
     })?;
     table.set("dlopen", dlopen_fn)?;
 
+    let dlopen_bytes_fn = lua.create_function(|_, image: LuaString| {
+        let handle = dlopen_bytes(&image.as_bytes())?;
+        Ok(LuaLightUserData(handle))
+    })?;
+    table.set("dlopenFromBytes", dlopen_bytes_fn)?;
+
     let dlsym_fn = lua.create_function(|lua, (handle, name): (LuaLightUserData, String)| {
         let c_name = CString::new(name.as_str())
-            .map_err(|_| LuaError::runtime(format!("Symbol name contains NUL byte: {name}")))?;
+            .map_err(|_| error::type_error(format!("Symbol name contains NUL byte: {name}")))?;
         let ptr = unsafe { luneffi_dlsym(handle.0, c_name.as_ptr()) };
         if ptr.is_null() {
-            let err = last_error().unwrap_or_else(|| "symbol lookup failed".to_string());
-            let err_value = LuaValue::String(lua.create_string(err)?);
+            let err_value = LuaValue::String(lua.create_string(dlsym_failure(handle.0, &name))?);
             Ok(LuaMultiValue::from_vec(vec![LuaValue::Nil, err_value]))
         } else {
             let symbol = LuaValue::LightUserData(LuaLightUserData(ptr));
@@ -549,100 +1496,375 @@ pub fn create(lua: &Lua) -> LuaResult<LuaTable> {
     })?;
     table.set("dlsym", dlsym_fn)?;
 
-    let dlclose_fn = lua.create_function(|_, handle: LuaLightUserData| {
-        let rc = unsafe { luneffi_dlclose(handle.0) };
-        if rc != 0 {
-            let err = last_error().unwrap_or_else(|| "dlclose failed".to_string());
-            return Err(LuaError::runtime(err));
-        }
-        Ok(())
+    // resolves a whole list in one crossing; failures land in a second table so one
+    // missing symbol does not hide the rest
+    let dlsym_many_fn =
+        lua.create_function(|lua, (handle, names): (LuaLightUserData, LuaTable)| {
+            let found = lua.create_table()?;
+            let failed = lua.create_table()?;
+            for name in names.sequence_values::<String>() {
+                let name = name?;
+                let Ok(c_name) = CString::new(name.as_str()) else {
+                    failed.set(
+                        name.as_str(),
+                        format!("Symbol name contains NUL byte: {name}"),
+                    )?;
+                    continue;
+                };
+                let ptr = unsafe { luneffi_dlsym(handle.0, c_name.as_ptr()) };
+                if ptr.is_null() {
+                    failed.set(name.as_str(), dlsym_failure(handle.0, &name))?;
+                } else {
+                    found.set(name, LuaLightUserData(ptr))?;
+                }
+            }
+            Ok((found, failed))
+        })?;
+    table.set("dlsymMany", dlsym_many_fn)?;
+
+    let exported_symbols_fn = lua.create_function(|lua, handle: LuaLightUserData| {
+        lua.create_sequence_from(exported_symbols(handle.0)?)
     })?;
+    table.set("exportedSymbols", exported_symbols_fn)?;
+
+    let dlsym_value_fn = lua.create_function(
+        |lua, (handle, name, code): (LuaLightUserData, String, String)| {
+            let c_name = CString::new(name.as_str())
+                .map_err(|_| error::type_error(format!("Symbol name contains NUL byte: {name}")))?;
+            let ptr = unsafe { luneffi_dlsym(handle.0, c_name.as_ptr()) };
+            if ptr.is_null() {
+                return Err(error::symbol(dlsym_failure(handle.0, &name)));
+            }
+            load_symbol_value(lua, ptr, &code)
+        },
+    )?;
+    table.set("dlsymValue", dlsym_value_fn)?;
+
+    let dlclose_fn =
+        lua.create_function(|_, (handle, force): (LuaLightUserData, Option<bool>)| {
+            close_handle(handle.0, force.unwrap_or(false))
+        })?;
     table.set("dlclose", dlclose_fn)?;
 
+    let open_library_fn = lua.create_function(|_, (path, flags): (String, Option<LuaTable>)| {
+        LibraryHandle::open(&path, open_flags(flags)?)
+    })?;
+    table.set("openLibrary", open_library_fn)?;
+
+    let open_existing_fn =
+        lua.create_function(|_, name: String| LibraryHandle::open_existing(&name))?;
+    table.set("openExisting", open_existing_fn)?;
+
     let errno_get_fn = lua.create_function(|_, ()| Ok(i64::from(get_errno())))?;
     table.set("getErrno", errno_get_fn)?;
 
     let errno_set_fn = lua.create_function(|_, value: LuaValue| {
         let coerced = types::lua_value_to_i64(&value)?;
         if coerced < c_int::MIN as i64 || coerced > c_int::MAX as i64 {
-            return Err(LuaError::runtime(
-                "errno value out of range for C int".to_string(),
-            ));
+            return Err(error::range("errno value out of range for C int"));
         }
         set_errno(coerced as c_int);
         Ok(())
     })?;
     table.set("setErrno", errno_set_fn)?;
 
+    let set_strict_fn = lua.create_function(|_, enabled: bool| {
+        let previous = types::is_strict();
+        types::set_strict(enabled);
+        Ok(previous)
+    })?;
+    table.set("setStrict", set_strict_fn)?;
+
     let alloc_fn = lua.create_function(|_, size: u64| {
         let bytes = usize::try_from(size)
-            .map_err(|_| LuaError::runtime("allocation size does not fit usize".to_string()))?;
+            .map_err(|_| error::range("allocation size does not fit usize"))?;
         let ptr = unsafe { calloc(1, bytes as size_t) };
         if ptr.is_null() && bytes > 0 {
-            return Err(LuaError::runtime(format!(
-                "failed to allocate {bytes} byte(s)"
-            )));
+            return Err(error::range(format!("failed to allocate {bytes} byte(s)")));
         }
+        track_alloc(ptr, bytes);
         Ok(LuaLightUserData(ptr))
     })?;
     table.set("alloc", alloc_fn)?;
 
-    let free_fn = lua.create_function(|_, ptr_value: LuaLightUserData| {
+    let alloc_stats_fn = lua.create_function(|lua, ()| {
+        let stats = lua.create_table()?;
+        stats.set(
+            "outstanding",
+            OUTSTANDING_ALLOCATIONS.load(Ordering::Relaxed),
+        )?;
+        stats.set("totalAllocated", TOTAL_ALLOCATED.load(Ordering::Relaxed))?;
+        stats.set("totalFreed", TOTAL_FREED.load(Ordering::Relaxed))?;
+        Ok(stats)
+    })?;
+    table.set("allocStats", alloc_stats_fn)?;
+
+    let free_fn = lua.create_function(|_, value: LuaValue| {
+        let target = match &value {
+            LuaValue::Nil => ptr::null_mut(),
+            LuaValue::LightUserData(ptr_value) => ptr_value.0,
+            LuaValue::Table(table) => {
+                // owned cdata are released by their finalizer, freeing them here would double free
+                if matches!(
+                    table.raw_get::<LuaValue>("__owned")?,
+                    LuaValue::Boolean(true)
+                ) {
+                    return Err(error::type_error(
+                        "cannot free memory owned by a managed cdata allocation",
+                    ));
+                }
+                // only the pointer the allocator handed out can be released
+                if cdata_offset(table)? != 0 {
+                    return Err(error::type_error(
+                        "cannot free a cdata offset from the start of its allocation",
+                    ));
+                }
+                lua_value_to_pointer(&value)?
+            }
+            other => {
+                return Err(error::type_error(format!(
+                    "free expects lightuserdata or cdata, got {other:?}"
+                )));
+            }
+        };
+
         unsafe {
-            if !ptr_value.0.is_null() {
-                free(ptr_value.0);
+            if !target.is_null() {
+                track_free(target);
+                free(target);
             }
         }
+
+        if let LuaValue::Table(table) = &value {
+            table.raw_set("__ptr", LuaValue::Nil)?;
+        }
         Ok(())
     })?;
     table.set("free", free_fn)?;
 
+    let run_finalizer_fn =
+        lua.create_function(|_, (func, ptr): (LuaLightUserData, LuaLightUserData)| {
+            if func.0.is_null() {
+                return Err(error::null_pointer(
+                    "native finalizer is a null function pointer",
+                ));
+            }
+            let finalizer: unsafe extern "C" fn(*mut c_void) =
+                unsafe { std::mem::transmute(func.0) };
+            unsafe { finalizer(ptr.0) };
+            Ok(())
+        })?;
+    table.set("runFinalizer", run_finalizer_fn)?;
+
     let store_fn = lua.create_function(
-        |_, (ptr_value, code, value): (LuaLightUserData, String, LuaValue)| {
-            let normalized = types::normalize_code(&code);
-            let ty = TypeCode::from_code(&normalized)?;
-            store_scalar(ptr_value.0, ty, &value)?;
+        |lua,
+         (ptr_value, spec, value, options): (
+            LuaLightUserData,
+            LuaValue,
+            LuaValue,
+            Option<LuaTable>,
+        )| {
+            let ty = scalar_type(lua, spec)?;
+            store_scalar(ptr_value.0, ty, &value, store_overflow(options)?)?;
             Ok(())
         },
     )?;
     table.set("storeScalar", store_fn)?;
 
-    let load_fn = lua.create_function(|lua, (ptr_value, code): (LuaLightUserData, String)| {
-        let normalized = types::normalize_code(&code);
-        let ty = TypeCode::from_code(&normalized)?;
-        load_scalar(lua, ptr_value.0, ty)
-    })?;
+    let load_fn = lua.create_function(
+        |lua, (ptr_value, spec, options): (LuaLightUserData, LuaValue, Option<LuaTable>)| {
+            let options = LoadOptions::from_table(options)?;
+            match spec {
+                LuaValue::Table(descriptor) => {
+                    load_described(lua, ptr_value.0, descriptor, options)
+                }
+                other => {
+                    let code = String::from_lua(other, lua)?;
+                    let normalized = types::normalize_code(&code);
+                    let ty = TypeCode::from_code(&normalized)?;
+                    load_scalar_with(lua, ptr_value.0, ty, options)
+                }
+            }
+        },
+    )?;
     table.set("loadScalar", load_fn)?;
 
-    let read_string_fn =
-        lua.create_function(|lua, (ptr_value, len): (LuaLightUserData, Option<u64>)| {
+    let buffer_store_fn = lua.create_function(
+        |lua,
+         (target, offset, spec, value, options): (
+            LuaValue,
+            LuaValue,
+            LuaValue,
+            LuaValue,
+            Option<LuaTable>,
+        )| {
+            let ty = scalar_type(lua, spec)?;
+            let slot = buffer_slot(&target, &offset, ty)?;
+            store_scalar(slot, ty, &value, store_overflow(options)?)
+        },
+    )?;
+    table.set("bufferStore", buffer_store_fn)?;
+
+    let buffer_load_fn = lua.create_function(
+        |lua, (target, offset, spec, options): (LuaValue, LuaValue, LuaValue, Option<LuaTable>)| {
+            let ty = scalar_type(lua, spec)?;
+            let slot = buffer_slot(&target, &offset, ty)?;
+            load_scalar_with(lua, slot, ty, LoadOptions::from_table(options)?)
+        },
+    )?;
+    table.set("bufferLoad", buffer_load_fn)?;
+
+    let load_strided_fn = lua.create_function(
+        |lua,
+         (ptr_value, code, count, stride, start_offset): (
+            LuaLightUserData,
+            String,
+            usize,
+            usize,
+            Option<usize>,
+        )| {
+            let ty = TypeCode::from_code(&types::normalize_code(&code))?;
+            if matches!(ty, TypeCode::Void | TypeCode::Struct) {
+                return Err(error::type_error(format!(
+                    "cannot load strided values of type '{code}'"
+                )));
+            }
+            if stride < ty.size_of() {
+                return Err(error::range(format!(
+                    "stride {stride} is smaller than the {} byte(s) of '{code}'",
+                    ty.size_of()
+                )));
+            }
+            if ptr_value.0.is_null() && count > 0 {
+                return Err(error::null_pointer("attempt to read from null pointer"));
+            }
+
+            let base = ptr_value.0 as *mut u8;
+            let start_offset = start_offset.unwrap_or(0);
+            let values = lua.create_table_with_capacity(count, 0)?;
+            for index in 0..count {
+                let offset = index
+                    .checked_mul(stride)
+                    .and_then(|offset| offset.checked_add(start_offset))
+                    .ok_or_else(|| error::range("strided offset overflows usize"))?;
+                let element = unsafe { base.add(offset) } as *mut c_void;
+                values.raw_set(index + 1, load_scalar(lua, element, ty)?)?;
+            }
+            Ok(values)
+        },
+    )?;
+    table.set("loadStrided", load_strided_fn)?;
+
+    let length_fn = lua.create_function(|_, value: LuaValue| {
+        let descriptor = match &value {
+            LuaValue::Table(table) => table.raw_get::<Option<LuaTable>>("__ctype")?,
+            _ => None,
+        };
+        let Some(descriptor) = descriptor else {
+            return Err(error::type_error("length expects array cdata"));
+        };
+        if descriptor.raw_get::<Option<String>>("kind")?.as_deref() != Some("array") {
+            let name = descriptor.raw_get::<Option<String>>("name")?;
+            return Err(error::type_error(format!(
+                "length expects array cdata, got '{}'",
+                name.as_deref().unwrap_or("?")
+            )));
+        }
+        descriptor.raw_get::<i64>("count")
+    })?;
+    table.set("length", length_fn)?;
+
+    let parse_int64_fn = lua.create_function(|lua, (text, base): (String, Option<u32>)| {
+        let (negative, magnitude) = types::parse_integer_literal(&text, base)?;
+        let value = if negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+        .ok_or_else(|| error::range(format!("integer literal '{text}' does not fit in int64")))?;
+        box_integer(lua, "long long", value as u64)
+    })?;
+    table.set("parseInt64", parse_int64_fn)?;
+
+    let parse_uint64_fn = lua.create_function(|lua, (text, base): (String, Option<u32>)| {
+        let (negative, magnitude) = types::parse_integer_literal(&text, base)?;
+        if negative {
+            return Err(error::range(format!(
+                "integer literal '{text}' is negative but uint64 is unsigned"
+            )));
+        }
+        box_integer(lua, "unsigned long long", magnitude)
+    })?;
+    table.set("parseUInt64", parse_uint64_fn)?;
+
+    let cdata_factory_fn = lua.create_function(|lua, factory: LuaFunction| {
+        lua.set_named_registry_value(CDATA_FACTORY_KEY, factory)
+    })?;
+    table.set("setCDataFactory", cdata_factory_fn)?;
+
+    let read_string_fn = lua.create_function(
+        |lua, (ptr_value, len, offset): (LuaLightUserData, Option<u64>, Option<u64>)| {
             if ptr_value.0.is_null() {
-                return Err(LuaError::runtime(
-                    "attempt to read string from null pointer".to_string(),
+                return Err(error::null_pointer(
+                    "attempt to read string from null pointer",
                 ));
             }
+            read_string(lua, byte_offset(ptr_value.0, offset)?, len)
+        },
+    )?;
+    table.set("readString", read_string_fn)?;
 
-            let bytes = match len {
-                Some(count) => {
-                    let count = usize::try_from(count).map_err(|_| {
-                        LuaError::runtime("string length does not fit usize".to_string())
-                    })?;
-                    unsafe { slice::from_raw_parts(ptr_value.0 as *const u8, count) }
-                }
-                None => unsafe { CStr::from_ptr(ptr_value.0 as *const c_char).to_bytes() },
-            };
+    let read_string_or_nil_fn = lua.create_function(
+        |lua, (ptr_value, len, offset): (LuaLightUserData, Option<u64>, Option<u64>)| {
+            if ptr_value.0.is_null() {
+                return Ok(LuaValue::Nil);
+            }
+            read_string(lua, byte_offset(ptr_value.0, offset)?, len)
+        },
+    )?;
+    table.set("readStringOrNil", read_string_or_nil_fn)?;
 
-            let lua_string = lua.create_string(bytes)?;
-            Ok(LuaValue::String(lua_string))
-        })?;
-    table.set("readString", read_string_fn)?;
+    let read_string_encoded_fn = lua.create_function(
+        |lua, (ptr_value, encoding, len): (LuaLightUserData, String, Option<u64>)| {
+            if ptr_value.0.is_null() {
+                return Err(error::null_pointer(
+                    "attempt to read string from null pointer",
+                ));
+            }
+            read_string_encoded(lua, ptr_value.0, &encoding, len)
+        },
+    )?;
+    table.set("readStringEncoded", read_string_encoded_fn)?;
+
+    let take_string_fn = lua.create_function(|lua, ptr_value: LuaLightUserData| {
+        if ptr_value.0.is_null() {
+            return Err(error::null_pointer(
+                "attempt to take string from null pointer",
+            ));
+        }
+
+        let lua_string = {
+            let bytes = unsafe { CStr::from_ptr(ptr_value.0 as *const c_char).to_bytes() };
+            lua.create_string(bytes)
+        };
+        track_free(ptr_value.0);
+        unsafe {
+            free(ptr_value.0);
+        }
+        Ok(LuaValue::String(lua_string?))
+    })?;
+    table.set("takeString", take_string_fn)?;
+
+    let describe_fn = lua.create_function(|_, value: LuaTable| describe_cdata(&value))?;
+    table.set("describe", describe_fn)?;
+
+    let type_of_fn = lua.create_function(|lua, value: LuaValue| type_of(lua, value))?;
+    table.set("typeOf", type_of_fn)?;
 
     let write_bytes_fn = lua.create_function(
         |_, (dest, data, append_null): (LuaLightUserData, LuaString, Option<bool>)| {
             if dest.0.is_null() {
-                return Err(LuaError::runtime(
-                    "attempt to write to null pointer".to_string(),
-                ));
+                return Err(error::null_pointer("attempt to write to null pointer"));
             }
 
             let bytes = data.as_bytes();
@@ -669,7 +1891,81 @@ pub fn create(lua: &Lua) -> LuaResult<LuaTable> {
     )?;
     table.set("call", call_fn)?;
 
+    let callv_fn = lua.create_function(
+        |lua, (func, signature, args): (LuaLightUserData, LuaTable, LuaMultiValue)| {
+            call::callv(lua, func, signature, args)
+        },
+    )?;
+    table.set("callv", callv_fn)?;
+
+    let call_into_fn =
+        lua.create_function(
+            |_,
+             (func, signature, args, destination): (
+                LuaLightUserData,
+                LuaTable,
+                LuaTable,
+                LuaValue,
+            )| { call::call_into(func, signature, args, destination) },
+        )?;
+    table.set("callInto", call_into_fn)?;
+
+    let try_call_fn = lua.create_function(
+        |lua, (func, signature, args): (LuaLightUserData, LuaTable, LuaTable)| {
+            call::try_call(lua, func, signature, args)
+        },
+    )?;
+    table.set("tryCall", try_call_fn)?;
+
+    let call_many_fn = lua.create_function(
+        |lua, (func, signature, arg_sets): (LuaLightUserData, LuaTable, LuaTable)| {
+            call::call_many(lua, func, signature, arg_sets)
+        },
+    )?;
+    table.set("callMany", call_many_fn)?;
+
+    let call_timed_fn = lua.create_function(
+        |lua, (func, signature, args): (LuaLightUserData, LuaTable, LuaTable)| {
+            call::call_timed(lua, func, signature, args)
+        },
+    )?;
+    table.set("callTimed", call_timed_fn)?;
+
+    let call_with_errno_reset_fn = lua.create_function(
+        |lua, (func, signature, args): (LuaLightUserData, LuaTable, LuaTable)| {
+            call::call_with_errno_reset(lua, func, signature, args)
+        },
+    )?;
+    table.set("callWithErrnoReset", call_with_errno_reset_fn)?;
+
+    let prepare_call_fn =
+        lua.create_function(|_, (func, signature): (LuaLightUserData, LuaTable)| {
+            call::prepare(func, signature)
+        })?;
+    table.set("prepareCall", prepare_call_fn)?;
+
+    let is_callable_fn = lua.create_function(|_, value: LuaValue| {
+        let ptr = lua_value_to_pointer(&value)?;
+        Ok(is_callable(ptr).unwrap_or(true))
+    })?;
+    table.set("isCallable", is_callable_fn)?;
+
+    let check_callable_fn =
+        lua.create_function(|_, func: LuaLightUserData| call::ensure_executable(func))?;
+    table.set("checkCallable", check_callable_fn)?;
+
+    let assert_type_fn = lua.create_function(|_, (value, code): (LuaValue, String)| {
+        Ok(call::assert_cdata_type(&value, &code)?
+            .map(|ptr| LuaValue::LightUserData(LuaLightUserData(ptr)))
+            .unwrap_or(LuaValue::Nil))
+    })?;
+    table.set("assertType", assert_type_fn)?;
+
     callback::register(lua, &table)?;
+    cdecl::register(lua, &table)?;
+    error::register(lua, &table)?;
+    memory::register(lua, &table)?;
+    signature::register(lua, &table)?;
 
     Ok(table)
 }
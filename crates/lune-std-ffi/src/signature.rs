@@ -2,40 +2,302 @@ use cfg_if::cfg_if;
 use libffi::middle::{self, Cif, Type};
 use mlua::prelude::*;
 
-use crate::types::{self, TypeCode};
+use crate::error;
+use crate::types::{self, Overflow, TypeCode};
 
 #[derive(Clone, Debug)]
 pub struct CType {
     pub(crate) code: TypeCode,
+    pub(crate) layout: Option<Box<RecordLayout>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordLayout {
+    pub(crate) fields: Vec<CType>,
+    pub(crate) offsets: Vec<usize>,
+    pub(crate) size: usize,
+    pub(crate) align: usize,
+    pub(crate) packed: bool,
+    pub(crate) union: bool,
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    if align <= 1 {
+        return value;
+    }
+    value.div_ceil(align) * align
+}
+
+fn read_alignment(table: &LuaTable) -> LuaResult<Option<usize>> {
+    match table.get::<Option<usize>>("align")? {
+        Some(align) if !align.is_power_of_two() => Err(error::type_error(format!(
+            "alignment {align} must be a positive power of two"
+        ))),
+        other => Ok(other),
+    }
+}
+
+impl RecordLayout {
+    fn from_fields(
+        fields: Vec<CType>,
+        field_aligns: &[Option<usize>],
+        packed: bool,
+        min_align: Option<usize>,
+    ) -> Self {
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut offset = 0;
+        let mut max_align = min_align.unwrap_or(1);
+
+        for (field, field_align) in fields.iter().zip(field_aligns) {
+            let natural = if packed { 1 } else { field.align() };
+            let align = natural.max(field_align.unwrap_or(1));
+            offset = align_up(offset, align);
+            offsets.push(offset);
+            offset += field.size();
+            max_align = max_align.max(align);
+        }
+
+        Self {
+            fields,
+            offsets,
+            size: align_up(offset, max_align),
+            align: max_align,
+            packed,
+            union: false,
+        }
+    }
+
+    fn union_of(
+        fields: Vec<CType>,
+        field_aligns: &[Option<usize>],
+        min_align: Option<usize>,
+    ) -> Self {
+        let mut size = 0;
+        let mut max_align = min_align.unwrap_or(1);
+        for (field, field_align) in fields.iter().zip(field_aligns) {
+            size = size.max(field.size());
+            max_align = max_align.max(field.align().max(field_align.unwrap_or(1)));
+        }
+
+        Self {
+            offsets: vec![0; fields.len()],
+            fields,
+            size: align_up(size, max_align),
+            align: max_align,
+            packed: false,
+            union: true,
+        }
+    }
+
+    // libffi has no unions either. Members that are all the same floating type keep the
+    // record in floating registers; anything else is classified as integer words, which
+    // is how the SysV and AArch64 rules merge mixed members.
+    fn union_elements(&self) -> Vec<Type> {
+        if let Some(code) = self.uniform_float() {
+            let unit = CType::primitive(code);
+            return std::iter::repeat_n(unit.to_libffi_type(), self.size / unit.size()).collect();
+        }
+        let word = match self.align {
+            1 => Type::u8(),
+            2 => Type::u16(),
+            4 => Type::u32(),
+            _ => Type::u64(),
+        };
+        let word_size = self.align.min(8);
+        std::iter::repeat_n(word, self.size / word_size).collect()
+    }
+
+    fn uniform_float(&self) -> Option<TypeCode> {
+        let first = self.fields.first()?.uniform_float()?;
+        self.fields
+            .iter()
+            .all(|field| field.uniform_float() == Some(first))
+            .then_some(first)
+    }
+
+    // libffi has no notion of packing, but a packed record whose fields all still sit at
+    // their natural offsets is laid out exactly like an ordinary one. Anything else (or
+    // a packed record nested where it would lose that alignment) would be misdescribed,
+    // so it cannot cross a call by value.
+    fn check_by_value(&self) -> LuaResult<()> {
+        let mut end = 0;
+        let mut natural_align = 1;
+        for (index, (field, &offset)) in self.fields.iter().zip(&self.offsets).enumerate() {
+            if offset % field.natural_align() != 0 {
+                return Err(error::type_error(format!(
+                    "struct field {} at offset {offset} is not naturally aligned; packed \
+                     records like this cannot be passed by value",
+                    index + 1
+                )));
+            }
+            end = offset + field.size();
+            natural_align = natural_align.max(field.natural_align());
+        }
+        if align_up(end, natural_align) > self.size {
+            return Err(error::type_error(format!(
+                "struct of {} bytes drops its tail padding; packed records like this \
+                 cannot be passed by value",
+                self.size
+            )));
+        }
+        Ok(())
+    }
+
+    // Alignment overrides can push fields (and the tail) past where libffi would place
+    // them, so explicit byte padding keeps libffi's view of the record in sync.
+    fn padded_elements(&self) -> Vec<Type> {
+        let mut elements = Vec::with_capacity(self.fields.len());
+        let mut natural = 0;
+        let mut natural_align = 1;
+        for (field, &offset) in self.fields.iter().zip(&self.offsets) {
+            natural = align_up(natural, field.natural_align());
+            elements.extend(std::iter::repeat_n(Type::u8(), offset - natural));
+            elements.push(field.to_libffi_type());
+            natural = offset + field.size();
+            natural_align = natural_align.max(field.natural_align());
+        }
+        let tail = align_up(natural, natural_align);
+        if self.size > tail {
+            elements.extend(std::iter::repeat_n(Type::u8(), self.size - tail));
+        }
+        elements
+    }
 }
 
 impl CType {
+    pub(crate) fn primitive(code: TypeCode) -> Self {
+        Self { code, layout: None }
+    }
+
     pub(crate) fn from_lua(value: LuaValue) -> LuaResult<Self> {
         match value {
             LuaValue::String(code) => {
                 let normalized = types::normalize_code(code.to_str()?.as_ref());
                 let ty = TypeCode::from_code(&normalized)?;
-                Ok(Self { code: ty })
+                Ok(Self::primitive(ty))
             }
             LuaValue::Table(table) => {
-                let code: String = table.get("code").map_err(|_| {
-                    LuaError::runtime("Type descriptor missing 'code' field".to_string())
-                })?;
+                let code: String = table
+                    .get("code")
+                    .map_err(|_| error::type_error("Type descriptor missing 'code' field"))?;
                 let normalized = types::normalize_code(&code);
-                let ty = TypeCode::from_code(&normalized)?;
-                Ok(Self { code: ty })
+                match normalized.as_str() {
+                    "struct" => Self::record_from_table(&table, false),
+                    "union" => Self::record_from_table(&table, true),
+                    "array" => Self::array_from_table(&table),
+                    _ => Ok(Self::primitive(TypeCode::from_code(&normalized)?)),
+                }
             }
-            other => Err(LuaError::runtime(format!(
+            other => Err(error::type_error(format!(
                 "Invalid type descriptor (expected table or string, got {other:?})"
             ))),
         }
     }
 
+    fn record_from_table(table: &LuaTable, union: bool) -> LuaResult<Self> {
+        let fields_table: LuaTable = table
+            .get("fields")
+            .map_err(|_| error::type_error("Struct descriptor missing 'fields' array"))?;
+
+        let mut fields = Vec::with_capacity(fields_table.raw_len() as usize);
+        let mut field_aligns = Vec::with_capacity(fields.capacity());
+        for field in fields_table.sequence_values::<LuaTable>() {
+            let field = field?;
+            if !matches!(field.get::<LuaValue>("bitWidth")?, LuaValue::Nil) {
+                return Err(error::type_error(
+                    "TODO(@lune/ffi/call): structs with bitfields cannot be passed by value yet",
+                ));
+            }
+            fields.push(CType::from_lua(field.get::<LuaValue>("ctype")?)?);
+            field_aligns.push(read_alignment(&field)?);
+        }
+
+        let packed = table.get::<Option<bool>>("packed")?.unwrap_or(false);
+        let min_align = read_alignment(table)?;
+        let layout = if union {
+            if packed {
+                return Err(error::type_error(
+                    "TODO(@lune/ffi/call): packed unions cannot be passed by value yet",
+                ));
+            }
+            RecordLayout::union_of(fields, &field_aligns, min_align)
+        } else {
+            let layout = RecordLayout::from_fields(fields, &field_aligns, packed, min_align);
+            layout.check_by_value()?;
+            layout
+        };
+        Ok(Self {
+            code: TypeCode::Struct,
+            layout: Some(Box::new(layout)),
+        })
+    }
+
+    // C passes arrays inside records exactly like that many consecutive fields, which
+    // is also how libffi wants them described
+    fn array_from_table(table: &LuaTable) -> LuaResult<Self> {
+        let base = CType::from_lua(
+            table
+                .get::<LuaValue>("base")
+                .map_err(|_| error::type_error("Array descriptor missing 'base' type"))?,
+        )?;
+        let count: usize = table
+            .get("count")
+            .map_err(|_| error::type_error("Array descriptor missing 'count'"))?;
+        if count == 0 {
+            return Err(error::type_error("arrays must have at least one element"));
+        }
+        let field_aligns = vec![None; count];
+        Ok(Self {
+            code: TypeCode::Struct,
+            layout: Some(Box::new(RecordLayout::from_fields(
+                vec![base; count],
+                &field_aligns,
+                false,
+                None,
+            ))),
+        })
+    }
+
+    // the floating type every scalar inside this type shares, if there is exactly one
+    fn uniform_float(&self) -> Option<TypeCode> {
+        match &self.layout {
+            Some(layout) => layout.uniform_float(),
+            None => matches!(self.code, TypeCode::Float32 | TypeCode::Float64).then_some(self.code),
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        match &self.layout {
+            Some(layout) => layout.size,
+            None => self.code.size_of(),
+        }
+    }
+
+    pub(crate) fn align(&self) -> usize {
+        match &self.layout {
+            Some(layout) => layout.align,
+            None => self.code.align_of(),
+        }
+    }
+
+    // the alignment libffi derives for this type, which knows nothing of packing
+    fn natural_align(&self) -> usize {
+        match &self.layout {
+            Some(layout) if layout.packed => layout
+                .fields
+                .iter()
+                .map(CType::natural_align)
+                .max()
+                .unwrap_or(1),
+            _ => self.align(),
+        }
+    }
+
     pub(crate) fn to_libffi_type(&self) -> Type {
         match self.code {
             TypeCode::Void => Type::void(),
             TypeCode::Int8 => Type::i8(),
-            TypeCode::UInt8 => Type::u8(),
+            TypeCode::Bool | TypeCode::UInt8 => Type::u8(),
             TypeCode::Int16 => Type::i16(),
             TypeCode::UInt16 => Type::u16(),
             TypeCode::Int32 => Type::i32(),
@@ -58,7 +320,13 @@ impl CType {
             }
             TypeCode::Float32 => Type::f32(),
             TypeCode::Float64 => Type::f64(),
+            TypeCode::LongDouble => Type::longdouble(),
             TypeCode::Pointer => Type::pointer(),
+            TypeCode::Struct => match &self.layout {
+                Some(layout) if layout.union => Type::structure(layout.union_elements()),
+                Some(layout) => Type::structure(layout.padded_elements()),
+                None => Type::structure(std::iter::empty::<Type>()),
+            },
         }
     }
 
@@ -91,7 +359,7 @@ impl AbiChoice {
                     } else if #[cfg(all(target_os = "windows", target_arch = "x86_64"))] {
                         Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_WIN64))
                     } else {
-                        Err(LuaError::runtime("ABI 'sysv' not supported on this target".to_string()))
+                        Err(error::type_error("ABI 'sysv' not supported on this target"))
                     }
                 }
             }
@@ -100,7 +368,20 @@ impl AbiChoice {
                     if #[cfg(any(target_arch = "x86"))] {
                         Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_STDCALL))
                     } else {
-                        Err(LuaError::runtime("ABI 'stdcall' requires x86 architecture".to_string()))
+                        Err(error::type_error("ABI 'stdcall' requires x86 architecture"))
+                    }
+                }
+            }
+            Some("thiscall") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_THISCALL))
+                    } else if #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))] {
+                        // 64-bit targets have no separate member calling convention; `this`
+                        // is simply the first integer argument
+                        Ok(AbiChoice::Default)
+                    } else {
+                        Err(error::type_error("ABI 'thiscall' not supported on this target"))
                     }
                 }
             }
@@ -111,7 +392,7 @@ impl AbiChoice {
                     } else if #[cfg(all(target_os = "windows", target_arch = "x86_64"))] {
                         Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_WIN64))
                     } else {
-                        Err(LuaError::runtime("ABI 'ms_abi' only available on Windows targets".to_string()))
+                        Err(error::type_error("ABI 'ms_abi' only available on Windows targets"))
                     }
                 }
             }
@@ -120,11 +401,80 @@ impl AbiChoice {
                     if #[cfg(target_os = "windows")] {
                         Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_WIN64))
                     } else {
-                        Err(LuaError::runtime("ABI 'win64' only available on Windows targets".to_string()))
+                        Err(error::type_error("ABI 'win64' only available on Windows targets"))
+                    }
+                }
+            }
+            Some("sysv_abi") | Some("unix64") => {
+                cfg_if! {
+                    if #[cfg(all(target_arch = "x86_64", unix))] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_UNIX64))
+                    } else {
+                        Err(error::type_error("ABI 'sysv_abi' requires a non-Windows x86_64 target"))
+                    }
+                }
+            }
+            Some("efi64") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86_64")] {
+                        // libffi defines EFI64 as an alias of WIN64
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_WIN64))
+                    } else {
+                        Err(error::type_error("ABI 'efi64' requires x86_64 architecture"))
+                    }
+                }
+            }
+            Some("gnuw64") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86_64")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_GNUW64))
+                    } else {
+                        Err(error::type_error("ABI 'gnuw64' requires x86_64 architecture"))
+                    }
+                }
+            }
+            Some("fastcall") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_FASTCALL))
+                    } else {
+                        Err(error::type_error("ABI 'fastcall' requires x86 architecture"))
+                    }
+                }
+            }
+            Some("pascal") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_PASCAL))
+                    } else {
+                        Err(error::type_error("ABI 'pascal' requires x86 architecture"))
+                    }
+                }
+            }
+            Some("register") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "x86")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_REGISTER))
+                    } else {
+                        Err(error::type_error("ABI 'register' requires x86 architecture"))
+                    }
+                }
+            }
+            Some("vfp") => {
+                cfg_if! {
+                    if #[cfg(target_arch = "arm")] {
+                        Ok(AbiChoice::Explicit(libffi::raw::ffi_abi_FFI_VFP))
+                    } else {
+                        Err(error::type_error("ABI 'vfp' requires 32-bit ARM architecture"))
                     }
                 }
             }
-            Some(other) => Err(LuaError::runtime(format!("Unsupported ABI '{other}'"))),
+            // GCC's regparm(N) passes the first N integer arguments in registers, which no
+            // libffi ABI describes; FFI_REGISTER is Borland's convention, not a substitute
+            Some("regparm") => Err(error::type_error(
+                "ABI 'regparm' has no libffi equivalent; declare the function without regparm",
+            )),
+            Some(other) => Err(error::type_error(format!("Unsupported ABI '{other}'"))),
         }
     }
 
@@ -143,11 +493,162 @@ pub struct Signature {
     pub(crate) args: Vec<CType>,
     pub(crate) variadic: bool,
     pub(crate) fixed_count: usize,
+    pub(crate) result_mode: ResultMode,
+    pub(crate) overflow: Overflow,
+    pub(crate) on_error: Option<ErrorCheck>,
+    pub(crate) borrow_strings: bool,
+    // pointer arguments refuse plain numbers, so a stray integer cannot become an address
+    pub(crate) strict_pointers: bool,
+    // control never comes back (`exit`, `abort`, `longjmp`), so no result is read
+    pub(crate) noreturn: bool,
+    // pointer argument (0-based, implicit `this` included) that receives a `size_t`
+    // length; the pointer result is then read as a Lua string of that many bytes
+    pub(crate) result_length: Option<usize>,
+}
+
+// how a call's result is handed to Lua, from the signature's `resultMode`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultMode {
+    Raw,
+    // `char*` results as Lua strings (nil when null), 64-bit integers as decimal text
+    String,
+    // integer and pointer results as `value ~= 0`
+    Boolean,
+    // pointer results as their address
+    Integer,
+    // 64-bit integer results as `long long`/`unsigned long long` cdata
+    Box,
+}
+
+impl ResultMode {
+    fn parse(name: &str) -> LuaResult<Self> {
+        match name {
+            "raw" => Ok(ResultMode::Raw),
+            "string" => Ok(ResultMode::String),
+            "boolean" => Ok(ResultMode::Boolean),
+            "integer" => Ok(ResultMode::Integer),
+            "box" => Ok(ResultMode::Box),
+            other => Err(error::type_error(format!(
+                "unknown resultMode '{other}' (expected 'raw', 'string', 'boolean', 'integer', or 'box')"
+            ))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ResultMode::Raw => "raw",
+            ResultMode::String => "string",
+            ResultMode::Boolean => "boolean",
+            ResultMode::Integer => "integer",
+            ResultMode::Box => "box",
+        }
+    }
+
+    fn accepts(self, code: TypeCode) -> bool {
+        let wide = matches!(
+            code,
+            TypeCode::Int64 | TypeCode::UInt64 | TypeCode::IntPtr | TypeCode::UIntPtr
+        );
+        let integer = wide
+            || matches!(
+                code,
+                TypeCode::Int8
+                    | TypeCode::UInt8
+                    | TypeCode::Int16
+                    | TypeCode::UInt16
+                    | TypeCode::Int32
+                    | TypeCode::UInt32
+            );
+        match self {
+            ResultMode::Raw => true,
+            ResultMode::String => wide || code == TypeCode::Pointer,
+            ResultMode::Boolean => integer || matches!(code, TypeCode::Bool | TypeCode::Pointer),
+            ResultMode::Integer => code == TypeCode::Pointer,
+            ResultMode::Box => wide,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCondition {
+    Negative,
+    MinusOne,
+    Zero,
+    NonZero,
+    Null,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorCheck {
+    pub(crate) when: ErrorCondition,
+    pub(crate) read_errno: bool,
+    // false records the failure for `lastError` and hands back the sentinel result
+    pub(crate) raise: bool,
+}
+
+impl ErrorCheck {
+    pub(crate) fn from_table(table: LuaTable) -> LuaResult<Self> {
+        let when = match table.get::<Option<String>>("when")?.as_deref() {
+            Some("negative") => ErrorCondition::Negative,
+            Some("minusOne") => ErrorCondition::MinusOne,
+            Some("zero") => ErrorCondition::Zero,
+            Some("nonzero") => ErrorCondition::NonZero,
+            Some("null") => ErrorCondition::Null,
+            Some(other) => {
+                return Err(error::type_error(format!(
+                    "unknown onError condition '{other}'"
+                )));
+            }
+            None => return Err(error::type_error("onError requires a 'when' condition")),
+        };
+        let read_errno = match table.get::<Option<String>>("read")?.as_deref() {
+            Some("errno") => true,
+            None => false,
+            Some(other) => {
+                return Err(error::type_error(format!(
+                    "unknown onError read source '{other}' (expected 'errno')"
+                )));
+            }
+        };
+        let raise = table.get::<Option<bool>>("raise")?.unwrap_or(true);
+        Ok(Self {
+            when,
+            read_errno,
+            raise,
+        })
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self.when {
+            ErrorCondition::Negative => "negative",
+            ErrorCondition::MinusOne => "minusOne",
+            ErrorCondition::Zero => "zero",
+            ErrorCondition::NonZero => "nonzero",
+            ErrorCondition::Null => "null",
+        }
+    }
+
+    pub(crate) fn matches(&self, value: &LuaValue) -> bool {
+        match (self.when, value) {
+            (ErrorCondition::Negative, LuaValue::Integer(n)) => *n < 0,
+            (ErrorCondition::Negative, LuaValue::Number(n)) => *n < 0.0,
+            (ErrorCondition::MinusOne, LuaValue::Integer(n)) => *n == -1,
+            (ErrorCondition::MinusOne, LuaValue::Number(n)) => *n == -1.0,
+            (ErrorCondition::Zero | ErrorCondition::Null, LuaValue::Integer(n)) => *n == 0,
+            (ErrorCondition::Zero | ErrorCondition::Null, LuaValue::Number(n)) => *n == 0.0,
+            (ErrorCondition::Zero | ErrorCondition::Null, LuaValue::Nil) => true,
+            (ErrorCondition::NonZero, LuaValue::Integer(n)) => *n != 0,
+            (ErrorCondition::NonZero, LuaValue::Number(n)) => *n != 0.0,
+            (ErrorCondition::NonZero, LuaValue::LightUserData(_)) => true,
+            _ => false,
+        }
+    }
 }
 
 impl Signature {
     pub(crate) fn from_table(table: LuaTable) -> LuaResult<Self> {
-        let abi = AbiChoice::from_option(table.get::<Option<String>>("abi")?)?;
+        let abi_name = table.get::<Option<String>>("abi")?;
+        let abi = AbiChoice::from_option(abi_name.clone())?;
         let result_value: LuaValue = table.get("result")?;
         let result = CType::from_lua(result_value)?;
 
@@ -155,25 +656,182 @@ impl Signature {
         let mut args = Vec::with_capacity(args_table.raw_len() as usize);
         for value in args_table.sequence_values::<LuaValue>() {
             let value = value?;
-            args.push(CType::from_lua(value)?);
+            let ty = CType::from_lua(value)?;
+            if ty.code() == TypeCode::Void {
+                return Err(error::type_error(format!(
+                    "Invalid signature: argument {} cannot be void",
+                    args.len() + 1
+                )));
+            }
+            args.push(ty);
         }
 
         let variadic = table.get::<Option<bool>>("variadic")?.unwrap_or(false);
-        let fixed_count = table
-            .get::<Option<u32>>("fixedCount")?
-            .map_or(args.len(), |n| n as usize);
+        let mut fixed_count = table.get::<Option<u32>>("fixedCount")?.map(|n| n as usize);
+
+        // `sequence_values` stops at the first hole, so a gap (or a fixedCount past the
+        // end) would otherwise drop arguments silently or fail later with a bare count
+        let last_index = args_table
+            .pairs::<LuaValue, LuaValue>()
+            .filter_map(|pair| match pair {
+                Ok((LuaValue::Integer(index), _)) => usize::try_from(index).ok(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        if last_index.max(fixed_count.unwrap_or(0)) > args.len() {
+            return Err(error::type_error(format!(
+                "Invalid signature: argument {} has no type",
+                args.len() + 1
+            )));
+        }
+
+        if abi_name.as_deref() == Some("thiscall") && variadic {
+            return Err(error::type_error(
+                "Invalid signature: thiscall functions cannot be variadic",
+            ));
+        }
+        let member_function = table
+            .get::<Option<bool>>("memberFunction")?
+            .unwrap_or(false);
+        match abi_name.as_deref() {
+            None | Some("cdecl") | Some("default") => {}
+            Some("thiscall") => {
+                // 64-bit targets map thiscall onto the default convention, which only
+                // matches a member function if `this` is passed first
+                if cfg!(not(target_arch = "x86")) && !member_function {
+                    return Err(error::type_error(
+                        "Invalid signature: ABI 'thiscall' requires memberFunction = true on this target",
+                    ));
+                }
+            }
+            Some(other) if member_function => {
+                return Err(error::type_error(format!(
+                    "Invalid signature: memberFunction cannot be used with ABI '{other}'"
+                )));
+            }
+            Some(_) => {}
+        }
+        let declared_count = fixed_count.unwrap_or(args.len());
+        if member_function {
+            // the object pointer is passed as an implicit leading argument
+            args.insert(0, CType::primitive(TypeCode::Pointer));
+            fixed_count = fixed_count.map(|n| n + 1);
+        }
+
+        let mut signature = Self::from_parts(abi, result, args, variadic, fixed_count)?;
+        let legacy_integer = match table.get::<Option<LuaTable>>("resultOptions")? {
+            Some(options) => options.get::<Option<bool>>("asInteger")?.unwrap_or(false),
+            None => false,
+        };
+        if let Some(mode) = table.get::<Option<String>>("resultMode")? {
+            let mode = ResultMode::parse(&mode)?;
+            if legacy_integer && mode != ResultMode::Integer {
+                return Err(error::type_error(format!(
+                    "resultOptions.asInteger conflicts with resultMode '{}'",
+                    mode.name()
+                )));
+            }
+            if !mode.accepts(signature.result.code()) {
+                return Err(error::type_error(format!(
+                    "resultMode '{}' cannot be used with a {} result",
+                    mode.name(),
+                    signature.result.code().name()
+                )));
+            }
+            signature.result_mode = mode;
+        } else if legacy_integer && signature.result.code() == TypeCode::Pointer {
+            // the older spelling of `resultMode = "integer"`, ignored on other results
+            signature.result_mode = ResultMode::Integer;
+        }
+        if table.get::<Option<bool>>("wrap")?.unwrap_or(false) {
+            signature.overflow = Overflow::Wrap;
+        }
+        signature.borrow_strings = table.get::<Option<bool>>("borrowStrings")?.unwrap_or(false);
+        signature.strict_pointers = table
+            .get::<Option<bool>>("strictPointers")?
+            .unwrap_or(false);
+        if table.get::<Option<bool>>("noreturn")?.unwrap_or(false) {
+            if signature.result.code() != TypeCode::Void {
+                return Err(error::type_error(format!(
+                    "noreturn functions must declare a void result, not {}",
+                    signature.result.code().name()
+                )));
+            }
+            signature.noreturn = true;
+        }
+        if let Some(position) = table.get::<Option<usize>>("resultLength")? {
+            if position == 0 || position > declared_count {
+                return Err(error::type_error(format!(
+                    "resultLength names argument {position}, but the signature declares \
+                     {declared_count} fixed argument(s)"
+                )));
+            }
+            let index = position - 1 + usize::from(member_function);
+            if signature.args[index].code() != TypeCode::Pointer {
+                return Err(error::type_error(format!(
+                    "resultLength argument {position} must be a pointer to the length, not {}",
+                    signature.args[index].code().name()
+                )));
+            }
+            if signature.result.code() != TypeCode::Pointer {
+                return Err(error::type_error(format!(
+                    "resultLength needs a pointer result, not {}",
+                    signature.result.code().name()
+                )));
+            }
+            if signature.result_mode != ResultMode::Raw {
+                return Err(error::type_error(format!(
+                    "resultLength cannot be combined with resultMode '{}'",
+                    signature.result_mode.name()
+                )));
+            }
+            signature.result_length = Some(index);
+        }
+        if let Some(check) = table.get::<Option<LuaTable>>("onError")? {
+            if signature.result.code() == TypeCode::Void {
+                return Err(error::type_error(
+                    "onError cannot be used with a void result",
+                ));
+            }
+            if signature.result_length.is_some() {
+                return Err(error::type_error(
+                    "onError cannot be combined with resultLength",
+                ));
+            }
+            if matches!(
+                signature.result_mode,
+                ResultMode::String | ResultMode::Boolean | ResultMode::Box
+            ) {
+                return Err(error::type_error(format!(
+                    "onError cannot be combined with resultMode '{}'",
+                    signature.result_mode.name()
+                )));
+            }
+            signature.on_error = Some(ErrorCheck::from_table(check)?);
+        }
+        Ok(signature)
+    }
+
+    pub(crate) fn from_parts(
+        abi: AbiChoice,
+        result: CType,
+        args: Vec<CType>,
+        variadic: bool,
+        fixed_count: Option<usize>,
+    ) -> LuaResult<Self> {
+        let fixed_count = fixed_count.unwrap_or(args.len());
 
         if fixed_count > args.len() {
-            return Err(LuaError::runtime(format!(
+            return Err(error::type_error(format!(
                 "Invalid signature: fixedCount ({fixed_count}) exceeds number of arguments ({})",
                 args.len()
             )));
         }
 
         if !variadic && fixed_count != args.len() {
-            return Err(LuaError::runtime(
-                "Invalid signature: fixedCount must equal number of arguments for non-variadic functions"
-                    .to_string(),
+            return Err(error::type_error(
+                "Invalid signature: fixedCount must equal number of arguments for non-variadic functions",
             ));
         }
 
@@ -183,6 +841,13 @@ impl Signature {
             args,
             variadic,
             fixed_count,
+            result_mode: ResultMode::Raw,
+            overflow: Overflow::Error,
+            on_error: None,
+            borrow_strings: false,
+            strict_pointers: false,
+            noreturn: false,
+            result_length: None,
         })
     }
 
@@ -202,14 +867,52 @@ impl Signature {
         self.fixed_count
     }
 
+    pub(crate) fn result_mode(&self) -> ResultMode {
+        self.result_mode
+    }
+
+    pub(crate) fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub(crate) fn on_error(&self) -> Option<ErrorCheck> {
+        self.on_error
+    }
+
+    pub(crate) fn borrow_strings(&self) -> bool {
+        self.borrow_strings
+    }
+
+    pub(crate) fn strict_pointers(&self) -> bool {
+        self.strict_pointers
+    }
+
+    pub(crate) fn is_noreturn(&self) -> bool {
+        self.noreturn
+    }
+
+    pub(crate) fn result_length(&self) -> Option<usize> {
+        self.result_length
+    }
+
     pub(crate) fn arg_types(&self) -> Vec<Type> {
         self.args.iter().map(CType::to_libffi_type).collect()
     }
 
-    pub(crate) fn build_cif(&self, arg_types: &[Type]) -> Cif {
+    pub(crate) fn build_cif(&self, arg_types: &[Type]) -> LuaResult<Cif> {
         let result_type = self.result.to_libffi_type();
 
-        let mut cif = if self.variadic {
+        if self.variadic && arg_types.len() < self.fixed_count {
+            return Err(error::type_error(format!(
+                "variadic call needs at least {} fixed argument type(s) but received {}",
+                self.fixed_count,
+                arg_types.len()
+            )));
+        }
+
+        // a bare `(...)` call with nothing to pass has no variadic part, and some libffi
+        // ports reject prep_cif_var with zero arguments, so it gets a plain empty cif
+        let mut cif = if self.variadic && !arg_types.is_empty() {
             Cif::new_variadic(arg_types.iter().cloned(), self.fixed_count, result_type)
         } else {
             Cif::new(arg_types.iter().cloned(), result_type)
@@ -219,6 +922,108 @@ impl Signature {
             cif.set_abi(explicit);
         }
 
-        cif
+        Ok(cif)
     }
 }
+
+// incremental front end for `Signature::from_table`: each step checks what it can so
+// a mistake is reported by the call that made it rather than at build time
+#[derive(Default)]
+pub struct SignatureBuilder {
+    result: Option<String>,
+    args: Vec<String>,
+    fixed_count: Option<usize>,
+}
+
+fn builder_code(code: &str) -> LuaResult<String> {
+    let normalized = types::normalize_code(code);
+    TypeCode::from_code(&normalized)?;
+    Ok(normalized)
+}
+
+impl SignatureBuilder {
+    pub(crate) fn set_result(&mut self, code: &str) -> LuaResult<()> {
+        if self.result.is_some() {
+            return Err(error::type_error("signature result type is already set"));
+        }
+        self.result = Some(builder_code(code)?);
+        Ok(())
+    }
+
+    pub(crate) fn push_arg(&mut self, code: &str) -> LuaResult<()> {
+        if self.fixed_count.is_some() {
+            return Err(error::type_error(
+                "arguments must be added before the signature is marked variadic",
+            ));
+        }
+        let code = builder_code(code)?;
+        if code == "void" {
+            return Err(error::type_error(format!(
+                "argument {} cannot be void",
+                self.args.len() + 1
+            )));
+        }
+        self.args.push(code);
+        Ok(())
+    }
+
+    pub(crate) fn set_variadic(&mut self, fixed_count: Option<usize>) -> LuaResult<()> {
+        if self.fixed_count.is_some() {
+            return Err(error::type_error("signature is already variadic"));
+        }
+        let fixed_count = fixed_count.unwrap_or(self.args.len());
+        if fixed_count > self.args.len() {
+            return Err(error::type_error(format!(
+                "fixedCount ({fixed_count}) exceeds the {} argument(s) added so far",
+                self.args.len()
+            )));
+        }
+        self.fixed_count = Some(fixed_count);
+        Ok(())
+    }
+
+    pub(crate) fn build(&self, lua: &Lua) -> LuaResult<LuaTable> {
+        let Some(result) = &self.result else {
+            return Err(error::type_error("signature has no result type"));
+        };
+
+        let table = lua.create_table()?;
+        table.set("result", result.as_str())?;
+        table.set(
+            "args",
+            lua.create_sequence_from(self.args.iter().map(String::as_str))?,
+        )?;
+        table.set("variadic", self.fixed_count.is_some())?;
+        table.set("fixedCount", self.fixed_count.unwrap_or(self.args.len()))?;
+        Signature::from_table(table.clone())?;
+        Ok(table)
+    }
+}
+
+impl LuaUserData for SignatureBuilder {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // every step hands the builder back so calls can be chained
+        methods.add_function("result", |_, (this, code): (LuaAnyUserData, String)| {
+            this.borrow_mut::<Self>()?.set_result(&code)?;
+            Ok(this)
+        });
+        methods.add_function("arg", |_, (this, code): (LuaAnyUserData, String)| {
+            this.borrow_mut::<Self>()?.push_arg(&code)?;
+            Ok(this)
+        });
+        methods.add_function(
+            "variadic",
+            |_, (this, fixed_count): (LuaAnyUserData, Option<usize>)| {
+                this.borrow_mut::<Self>()?.set_variadic(fixed_count)?;
+                Ok(this)
+            },
+        );
+        methods.add_method("build", |lua, this, ()| this.build(lua));
+    }
+}
+
+pub fn register(lua: &Lua, exports: &LuaTable) -> LuaResult<()> {
+    let new_fn = lua.create_function(|_, ()| Ok(SignatureBuilder::default()))?;
+    exports.set("newSignature", new_fn)?;
+    Ok(())
+}
@@ -1,10 +1,15 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::ffi::c_void;
 
 use mlua::prelude::*;
 
+use crate::error;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TypeCode {
     Void,
+    Bool,
     Int8,
     UInt8,
     Int16,
@@ -17,50 +22,153 @@ pub enum TypeCode {
     UIntPtr,
     Float32,
     Float64,
+    // only for targets where `long double` is wider than `double`
+    LongDouble,
     Pointer,
+    Struct,
+}
+
+thread_local! {
+    // script-registered spellings, normalized name -> normalized target; registration
+    // keeps the chains acyclic, so every lookup ends at a built-in code
+    static ALIASES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+fn aliased(code: &str) -> Option<TypeCode> {
+    ALIASES.with(|aliases| {
+        let aliases = aliases.borrow();
+        let mut current = aliases.get(code)?;
+        loop {
+            if let Some(ty) = TypeCode::builtin(current) {
+                return Some(ty);
+            }
+            current = aliases.get(current)?;
+        }
+    })
+}
+
+pub fn register_alias(name: &str, target: &str) -> LuaResult<TypeCode> {
+    let name = normalize_code(name);
+    let target = normalize_code(target);
+    if name.is_empty() {
+        return Err(error::type_error("type alias name must not be empty"));
+    }
+    if TypeCode::builtin(&name).is_some() {
+        return Err(error::type_error(format!(
+            "cannot alias built-in type code '{name}'"
+        )));
+    }
+
+    ALIASES.with(|aliases| {
+        let mut aliases = aliases.borrow_mut();
+        let mut current = target.clone();
+        loop {
+            if current == name {
+                return Err(error::type_error(format!(
+                    "alias '{name}' -> '{target}' would form a cycle"
+                )));
+            }
+            if let Some(ty) = TypeCode::builtin(&current) {
+                aliases.insert(name, target);
+                return Ok(ty);
+            }
+            match aliases.get(&current) {
+                Some(next) => current = next.clone(),
+                None => {
+                    return Err(error::type_error(format!(
+                        "Unsupported primitive type code '{target}'"
+                    )));
+                }
+            }
+        }
+    })
 }
 
 impl TypeCode {
     pub fn from_code(code: &str) -> LuaResult<Self> {
-        match code {
-            "void" => Ok(TypeCode::Void),
-            "int8" | "sint8" => Ok(TypeCode::Int8),
-            "uint8" => Ok(TypeCode::UInt8),
-            "int16" | "sint16" => Ok(TypeCode::Int16),
-            "uint16" => Ok(TypeCode::UInt16),
-            "int32" | "sint32" | "int" => Ok(TypeCode::Int32),
-            "uint32" | "unsigned int" => Ok(TypeCode::UInt32),
-            "int64" | "sint64" | "long long" => Ok(TypeCode::Int64),
-            "uint64" | "unsigned long long" => Ok(TypeCode::UInt64),
+        Self::builtin(code)
+            .or_else(|| aliased(code))
+            .ok_or_else(|| error::type_error(format!("Unsupported primitive type code '{code}'")))
+    }
+
+    fn builtin(code: &str) -> Option<Self> {
+        let ty = match code {
+            "void" => TypeCode::Void,
+            "char" => {
+                if CHAR_SIGNED {
+                    TypeCode::Int8
+                } else {
+                    TypeCode::UInt8
+                }
+            }
+            "bool" | "_bool" => TypeCode::Bool,
+            "int8" | "sint8" => TypeCode::Int8,
+            "uint8" => TypeCode::UInt8,
+            "int16" | "sint16" => TypeCode::Int16,
+            "uint16" | "char16_t" => TypeCode::UInt16,
+            "int32" | "sint32" | "int" => TypeCode::Int32,
+            "uint32" | "unsigned int" | "char32_t" => TypeCode::UInt32,
+            "int64" | "sint64" | "long long" => TypeCode::Int64,
+            "uint64" | "unsigned long long" => TypeCode::UInt64,
             "long" => {
                 if cfg!(target_pointer_width = "64") && !cfg!(target_os = "windows") {
-                    Ok(TypeCode::Int64)
+                    TypeCode::Int64
                 } else {
-                    Ok(TypeCode::Int32)
+                    TypeCode::Int32
                 }
             }
             "unsigned long" => {
                 if cfg!(target_pointer_width = "64") && !cfg!(target_os = "windows") {
-                    Ok(TypeCode::UInt64)
+                    TypeCode::UInt64
                 } else {
-                    Ok(TypeCode::UInt32)
+                    TypeCode::UInt32
                 }
             }
-            "size_t" | "uintptr_t" => Ok(TypeCode::UIntPtr),
-            "ssize_t" | "intptr_t" | "ptrdiff_t" => Ok(TypeCode::IntPtr),
-            "float" => Ok(TypeCode::Float32),
-            "double" => Ok(TypeCode::Float64),
-            "pointer" | "void*" => Ok(TypeCode::Pointer),
-            other => Err(LuaError::runtime(format!(
-                "Unsupported primitive type code '{other}'"
-            ))),
+            "wchar_t" => wchar_code(),
+            "size_t" | "uintptr_t" => TypeCode::UIntPtr,
+            "ssize_t" | "intptr_t" | "ptrdiff_t" => TypeCode::IntPtr,
+            "float" => TypeCode::Float32,
+            "double" => TypeCode::Float64,
+            "long double" => {
+                if long_double_is_double() {
+                    TypeCode::Float64
+                } else {
+                    TypeCode::LongDouble
+                }
+            }
+            "pointer" | "void*" => TypeCode::Pointer,
+            other if other.ends_with('*') => TypeCode::Pointer,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TypeCode::Void => "void",
+            TypeCode::Bool => "bool",
+            TypeCode::Int8 => "int8",
+            TypeCode::UInt8 => "uint8",
+            TypeCode::Int16 => "int16",
+            TypeCode::UInt16 => "uint16",
+            TypeCode::Int32 => "int32",
+            TypeCode::UInt32 => "uint32",
+            TypeCode::Int64 => "int64",
+            TypeCode::UInt64 => "uint64",
+            TypeCode::IntPtr => "intptr_t",
+            TypeCode::UIntPtr => "uintptr_t",
+            TypeCode::Float32 => "float",
+            TypeCode::Float64 => "double",
+            TypeCode::LongDouble => "long double",
+            TypeCode::Pointer => "pointer",
+            TypeCode::Struct => "struct",
         }
     }
 
     pub fn size_of(self) -> usize {
         match self {
-            TypeCode::Void => 0,
-            TypeCode::Int8 | TypeCode::UInt8 => std::mem::size_of::<i8>(),
+            TypeCode::Void | TypeCode::Struct => 0,
+            TypeCode::Bool | TypeCode::Int8 | TypeCode::UInt8 => std::mem::size_of::<i8>(),
             TypeCode::Int16 | TypeCode::UInt16 => std::mem::size_of::<i16>(),
             TypeCode::Int32 | TypeCode::UInt32 => std::mem::size_of::<i32>(),
             TypeCode::Int64 | TypeCode::UInt64 => std::mem::size_of::<i64>(),
@@ -69,13 +177,14 @@ impl TypeCode {
             }
             TypeCode::Float32 => std::mem::size_of::<f32>(),
             TypeCode::Float64 => std::mem::size_of::<f64>(),
+            TypeCode::LongDouble => long_double_layout().0,
         }
     }
 
     pub fn align_of(self) -> usize {
         match self {
-            TypeCode::Void => 1,
-            TypeCode::Int8 | TypeCode::UInt8 => std::mem::align_of::<i8>(),
+            TypeCode::Void | TypeCode::Struct => 1,
+            TypeCode::Bool | TypeCode::Int8 | TypeCode::UInt8 => std::mem::align_of::<i8>(),
             TypeCode::Int16 | TypeCode::UInt16 => std::mem::align_of::<i16>(),
             TypeCode::Int32 | TypeCode::UInt32 => std::mem::align_of::<i32>(),
             TypeCode::Int64 | TypeCode::UInt64 => std::mem::align_of::<i64>(),
@@ -84,12 +193,261 @@ impl TypeCode {
             }
             TypeCode::Float32 => std::mem::align_of::<f32>(),
             TypeCode::Float64 => std::mem::align_of::<f64>(),
+            TypeCode::LongDouble => long_double_layout().1,
+        }
+    }
+}
+
+// MSVC treats `long double` as `double`; GCC and Clang use a wider format (x87 extended
+// padded to 12 or 16 bytes, or IEEE quad), whose layout libffi was built to match
+fn long_double_layout() -> (usize, usize) {
+    if cfg!(target_env = "msvc") {
+        return (std::mem::size_of::<f64>(), std::mem::align_of::<f64>());
+    }
+    let ty = unsafe { &*std::ptr::addr_of!(libffi::low::types::longdouble) };
+    (ty.size, usize::from(ty.alignment))
+}
+
+// `wchar_t` is a 16-bit UTF-16 unit on Windows and a 32-bit code point elsewhere,
+// signed on most targets but unsigned on ARM Linux
+fn wchar_code() -> TypeCode {
+    let unsigned = libc::wchar_t::MIN == 0;
+    match (std::mem::size_of::<libc::wchar_t>(), unsigned) {
+        (2, _) => TypeCode::UInt16,
+        (_, true) => TypeCode::UInt32,
+        _ => TypeCode::Int32,
+    }
+}
+
+// plain `char` follows the target's C ABI: signed on x86, unsigned on most ARM targets
+pub const CHAR_SIGNED: bool = std::ffi::c_char::MIN != 0;
+
+pub fn long_double_is_double() -> bool {
+    long_double_layout().0 == std::mem::size_of::<f64>()
+}
+
+pub fn long_double_unsupported() -> LuaError {
+    error::type_error(
+        "TODO(@lune/ffi/types): extended-precision long double values not supported yet",
+    )
+}
+
+enum LongDoubleFormat {
+    // x87 extended: 64-bit mantissa with an explicit integer bit, padded to 12 or 16 bytes
+    X87,
+    // IEEE binary128
+    Quad,
+}
+
+fn long_double_format() -> LuaResult<LongDoubleFormat> {
+    if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        Ok(LongDoubleFormat::X87)
+    } else if cfg!(any(target_arch = "powerpc", target_arch = "powerpc64")) {
+        // IBM double-double
+        Err(long_double_unsupported())
+    } else if long_double_layout().0 == 16 {
+        Ok(LongDoubleFormat::Quad)
+    } else {
+        Err(long_double_unsupported())
+    }
+}
+
+// multiplies by 2^exp in steps so intermediate powers never overflow or flush to zero
+fn scale_by_power_of_two(mut value: f64, mut exp: i32) -> f64 {
+    let power = |exp: i32| f64::from_bits(((exp + 1023) as u64) << 52);
+    while exp > 1023 && value.is_finite() {
+        value *= power(1023);
+        exp -= 1023;
+    }
+    while exp < -1022 && value != 0.0 {
+        value *= power(-1022);
+        exp += 1022;
+    }
+    value * power(exp.clamp(-1022, 1023))
+}
+
+// the parts of a finite, non-zero double as `mantissa * 2^exp` with bit 52 of the
+// mantissa set, so subnormals come out normalized
+fn split_double(bits: u64) -> (u64, i32) {
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    if biased == 0 {
+        let shift = fraction.leading_zeros() as i32 - 11;
+        (fraction << shift, -1074 - shift)
+    } else {
+        (fraction | (1 << 52), biased - 1075)
+    }
+}
+
+// stores `value` as the target's extended `long double`; padding bytes are zeroed
+pub fn write_long_double(ptr: *mut u8, value: f64) -> LuaResult<()> {
+    let format = long_double_format()?;
+    let size = long_double_layout().0;
+    let bits = value.to_bits();
+    let sign = (bits >> 63) as u16;
+    let biased = (bits >> 52) & 0x7ff;
+    let fraction = bits & ((1 << 52) - 1);
+
+    let bytes = unsafe { std::slice::from_raw_parts_mut(ptr, size) };
+    bytes.fill(0);
+    match format {
+        LongDoubleFormat::X87 => {
+            let (exponent, mantissa) = if value == 0.0 {
+                (0, 0)
+            } else if biased == 0x7ff {
+                (0x7fff, (1 << 63) | (fraction << 11))
+            } else {
+                let (mantissa, exp) = split_double(bits);
+                ((exp + 52 + 16383) as u16, mantissa << 11)
+            };
+            bytes[..8].copy_from_slice(&mantissa.to_le_bytes());
+            bytes[8..10].copy_from_slice(&((sign << 15) | exponent).to_le_bytes());
+        }
+        LongDoubleFormat::Quad => {
+            let (exponent, fraction) = if value == 0.0 {
+                (0u128, 0u128)
+            } else if biased == 0x7ff {
+                (0x7fff, u128::from(fraction) << 60)
+            } else {
+                let (mantissa, exp) = split_double(bits);
+                let fraction = mantissa & ((1 << 52) - 1);
+                ((exp + 52 + 16383) as u128, u128::from(fraction) << 60)
+            };
+            let quad = (u128::from(sign) << 127) | (exponent << 112) | fraction;
+            bytes.copy_from_slice(&quad.to_ne_bytes());
         }
     }
+    Ok(())
 }
 
+// reads the target's extended `long double`, rounding to the nearest double
+pub fn read_long_double(ptr: *const u8) -> LuaResult<f64> {
+    let format = long_double_format()?;
+    let size = long_double_layout().0;
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, size) };
+    let (negative, value) = match format {
+        LongDoubleFormat::X87 => {
+            let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+            let top = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
+            let exponent = i32::from(top & 0x7fff);
+            let value = if exponent == 0x7fff {
+                if mantissa << 1 == 0 {
+                    f64::INFINITY
+                } else {
+                    f64::NAN
+                }
+            } else {
+                // denormals share the exponent of the smallest normal
+                scale_by_power_of_two(mantissa as f64, exponent.max(1) - 16383 - 63)
+            };
+            (top & 0x8000 != 0, value)
+        }
+        LongDoubleFormat::Quad => {
+            let quad = u128::from_ne_bytes(bytes[..16].try_into().unwrap());
+            let exponent = ((quad >> 112) & 0x7fff) as i32;
+            let fraction = quad & ((1 << 112) - 1);
+            let value = if exponent == 0x7fff {
+                if fraction == 0 {
+                    f64::INFINITY
+                } else {
+                    f64::NAN
+                }
+            } else {
+                let mantissa = if exponent == 0 {
+                    fraction
+                } else {
+                    fraction | (1 << 112)
+                };
+                scale_by_power_of_two(mantissa as f64, exponent.max(1) - 16383 - 112)
+            };
+            (quad >> 127 != 0, value)
+        }
+    };
+    Ok(if negative { -value } else { value })
+}
+
+// qualifiers change nothing about how a value is marshaled, so codes copied from headers
+// ("const char *", "int * restrict") are accepted with them stripped
+const QUALIFIERS: &[&str] = &["const", "volatile", "restrict", "__restrict"];
+
 pub fn normalize_code(code: &str) -> String {
-    code.trim().to_ascii_lowercase()
+    let lowered = code.trim().to_ascii_lowercase().replace('*', " * ");
+    let mut normalized = String::with_capacity(lowered.len());
+    for word in lowered
+        .split_whitespace()
+        .filter(|word| !QUALIFIERS.contains(word))
+    {
+        if word != "*" && !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+    }
+    normalized
+}
+
+pub fn is_const_qualified(code: &str) -> bool {
+    code.to_ascii_lowercase()
+        .replace('*', " ")
+        .split_whitespace()
+        .any(|word| word == "const")
+}
+
+thread_local! {
+    // opt-in rejection of the conversions that would otherwise lose information quietly
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_strict(enabled: bool) {
+    STRICT.with(|strict| strict.set(enabled));
+}
+
+pub fn is_strict() -> bool {
+    STRICT.with(Cell::get)
+}
+
+// unsigned 64-bit values past `i64::MAX` only fit a Lua number rounded
+pub fn u64_to_lua(value: u64) -> LuaResult<LuaValue> {
+    if value <= i64::MAX as u64 {
+        return Ok(LuaValue::Integer(value as i64));
+    }
+    if is_strict() {
+        return Err(error::range(format!(
+            "unsigned value {value} cannot be represented exactly as a Lua number \
+             (strict mode); read it with boxUnsigned or asString"
+        )));
+    }
+    Ok(LuaValue::Number(value as f64))
+}
+
+pub fn integer_to_f64(value: i64) -> LuaResult<f64> {
+    let converted = value as f64;
+    if is_strict() && converted as i128 != i128::from(value) {
+        return Err(error::range(format!(
+            "integer {value} cannot be represented exactly as a double (strict mode)"
+        )));
+    }
+    Ok(converted)
+}
+
+pub fn integer_to_f32(value: i64) -> LuaResult<f32> {
+    let converted = value as f32;
+    if is_strict() && converted as i128 != i128::from(value) {
+        return Err(error::range(format!(
+            "integer {value} cannot be represented exactly as a float (strict mode)"
+        )));
+    }
+    Ok(converted)
+}
+
+// C `bool` takes Lua booleans and integers, where any nonzero integer is true
+pub fn lua_value_to_bool(value: &LuaValue) -> LuaResult<bool> {
+    match value {
+        LuaValue::Boolean(b) => Ok(*b),
+        LuaValue::Integer(_) | LuaValue::Number(_) => Ok(lua_value_to_i64(value)? != 0),
+        other => Err(error::type_error(format!(
+            "expected boolean or integer for bool, got {other:?}"
+        ))),
+    }
 }
 
 pub fn lua_value_to_i64(value: &LuaValue) -> LuaResult<i64> {
@@ -97,20 +455,16 @@ pub fn lua_value_to_i64(value: &LuaValue) -> LuaResult<i64> {
         LuaValue::Integer(i) => Ok(*i),
         LuaValue::Number(n) => {
             if !n.is_finite() {
-                return Err(LuaError::runtime(
-                    "numeric argument must be finite".to_string(),
-                ));
+                return Err(error::range("numeric argument must be finite"));
             }
             let truncated = n.trunc();
             if (truncated - n).abs() > f64::EPSILON {
-                return Err(LuaError::runtime(
-                    "numeric argument must be integral".to_string(),
-                ));
+                return Err(error::type_error("numeric argument must be integral"));
             }
             Ok(truncated as i64)
         }
         LuaValue::Boolean(b) => Ok(if *b { 1 } else { 0 }),
-        other => Err(LuaError::runtime(format!(
+        other => Err(error::type_error(format!(
             "expected numeric value, got {other:?}"
         ))),
     }
@@ -119,19 +473,97 @@ pub fn lua_value_to_i64(value: &LuaValue) -> LuaResult<i64> {
 pub fn lua_value_to_u64(value: &LuaValue) -> LuaResult<u64> {
     let signed = lua_value_to_i64(value)?;
     if signed < 0 {
-        return Err(LuaError::runtime(
-            "negative value provided for unsigned argument".to_string(),
-        ));
+        return Err(error::range(format!(
+            "negative value {signed} for unsigned integer"
+        )));
     }
     Ok(signed as u64)
 }
 
+// returns the sign and magnitude of an integer literal; a 0x/0o/0b prefix picks the base
+// unless one is given explicitly, in which case only a matching prefix is accepted
+pub fn parse_integer_literal(text: &str, base: Option<u32>) -> LuaResult<(bool, u64)> {
+    let trimmed = text.trim();
+    let (negative, unsigned) = match trimmed.as_bytes().first() {
+        Some(b'-') => (true, &trimmed[1..]),
+        Some(b'+') => (false, &trimmed[1..]),
+        _ => (false, trimmed),
+    };
+
+    let prefix = unsigned.get(..2).map(str::to_ascii_lowercase);
+    let prefixed = match prefix.as_deref() {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    let (radix, digits) = match (base, prefixed) {
+        (Some(base), _) if !(2..=36).contains(&base) => {
+            return Err(error::range(format!(
+                "integer base must be between 2 and 36 (got {base})"
+            )));
+        }
+        (Some(base), Some(radix)) if base == radix => (base, &unsigned[2..]),
+        (Some(base), _) => (base, unsigned),
+        (None, Some(radix)) => (radix, &unsigned[2..]),
+        (None, None) => (10, unsigned),
+    };
+
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return Err(error::type_error(format!(
+            "invalid integer literal '{text}'"
+        )));
+    }
+    let magnitude = u64::from_str_radix(digits, radix).map_err(|err| match err.kind() {
+        std::num::IntErrorKind::PosOverflow => {
+            error::range(format!("integer literal '{text}' does not fit in 64 bits"))
+        }
+        _ => error::type_error(format!("invalid base-{radix} integer literal '{text}'")),
+    })?;
+    Ok((negative && magnitude != 0, magnitude))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    Error,
+    Wrap,
+}
+
+pub fn to_signed(value: &LuaValue, bits: u32, overflow: Overflow) -> LuaResult<i64> {
+    let raw = lua_value_to_i64(value)?;
+    if bits >= 64 {
+        return Ok(raw);
+    }
+    match overflow {
+        Overflow::Error => clamp_signed(raw, bits),
+        Overflow::Wrap => {
+            let shift = 64 - bits;
+            Ok((raw << shift) >> shift)
+        }
+    }
+}
+
+pub fn to_unsigned(value: &LuaValue, bits: u32, overflow: Overflow) -> LuaResult<u64> {
+    match overflow {
+        Overflow::Error => clamp_unsigned(lua_value_to_u64(value)?, bits),
+        Overflow::Wrap => {
+            let raw = lua_value_to_i64(value)? as u64;
+            if bits >= 64 {
+                Ok(raw)
+            } else {
+                Ok(raw & ((1u64 << bits) - 1))
+            }
+        }
+    }
+}
+
 pub fn clamp_signed(value: i64, bits: u32) -> LuaResult<i64> {
     let min = -(1i64 << (bits - 1));
     let max = (1i64 << (bits - 1)) - 1;
     if value < min || value > max {
-        return Err(LuaError::runtime(format!(
-            "signed argument out of range for {bits}-bit integer"
+        return Err(error::range(format!(
+            "value {value} out of range for signed {bits}-bit integer"
         )));
     }
     Ok(value)
@@ -144,8 +576,8 @@ pub fn clamp_unsigned(value: u64, bits: u32) -> LuaResult<u64> {
         (1u64 << bits) - 1
     };
     if value > max {
-        return Err(LuaError::runtime(format!(
-            "unsigned argument out of range for {bits}-bit integer"
+        return Err(error::range(format!(
+            "value {value} out of range for unsigned {bits}-bit integer"
         )));
     }
     Ok(value)
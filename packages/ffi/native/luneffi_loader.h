@@ -5,11 +5,27 @@
 extern "C" {
 #endif
 
+/* flags for luneffi_dlopen_flags; Windows ignores both */
+#define LUNEFFI_OPEN_NOW 1
+#define LUNEFFI_OPEN_GLOBAL 2
+
 void* luneffi_dlopen(const char* path);
+void* luneffi_dlopen_flags(const char* path, int flags);
+/* a new reference to a module that is already loaded; NULL with no error when it is not */
+void* luneffi_dlopen_existing(const char* path);
 void* luneffi_dlsym(void* handle, const char* name);
 int luneffi_dlclose(void* handle);
 const char* luneffi_dlerror(void);
 
+/* 1 = inside executable code, 0 = null or clearly data, -1 = unknown */
+int luneffi_is_callable(const void* ptr);
+
+/* returns nonzero to stop the enumeration early */
+typedef int (*luneffi_symbol_visitor)(const char* name, void* context);
+
+/* 0 = every exported name was visited, -1 = unsupported or failed (see luneffi_dlerror) */
+int luneffi_exported_symbols(void* handle, luneffi_symbol_visitor visit, void* context);
+
 #ifdef __cplusplus
 }
 #endif
@@ -1,10 +1,25 @@
+#if defined(__linux__) && !defined(_GNU_SOURCE)
+#define _GNU_SOURCE
+#endif
+
 #include "luneffi_loader.h"
 
 #include <dlfcn.h>
 #include <errno.h>
 #include <pthread.h>
+#include <stdint.h>
 #include <string.h>
 
+#if defined(__linux__) || defined(__FreeBSD__) || defined(__NetBSD__) || defined(__OpenBSD__)
+#include <link.h>
+#define LUNEFFI_HAVE_PHDR 1
+#endif
+
+// glibc declares RTLD_DI_LINKMAP as an enumerator, so it cannot be tested directly
+#if defined(LUNEFFI_HAVE_PHDR) && (defined(__GLIBC__) || defined(__FreeBSD__) || defined(RTLD_DI_LINKMAP))
+#define LUNEFFI_HAVE_DLINFO 1
+#endif
+
 #ifndef RTLD_DEFAULT
 #define RTLD_DEFAULT ((void*)0)
 #endif
@@ -25,8 +40,14 @@ static void luneffi_set_error(const char* message) {
 }
 
 void* luneffi_dlopen(const char* path) {
+    return luneffi_dlopen_flags(path, 0);
+}
+
+void* luneffi_dlopen_flags(const char* path, int flags) {
     luneffi_set_error(NULL);
-    void* handle = dlopen(path, RTLD_LAZY | RTLD_LOCAL);
+    int mode = (flags & LUNEFFI_OPEN_NOW) ? RTLD_NOW : RTLD_LAZY;
+    mode |= (flags & LUNEFFI_OPEN_GLOBAL) ? RTLD_GLOBAL : RTLD_LOCAL;
+    void* handle = dlopen(path, mode);
     if (handle == NULL) {
         const char* err = dlerror();
         luneffi_set_error(err ? err : "unknown dlopen error");
@@ -34,6 +55,22 @@ void* luneffi_dlopen(const char* path) {
     return handle;
 }
 
+void* luneffi_dlopen_existing(const char* path) {
+    luneffi_set_error(NULL);
+#ifdef RTLD_NOLOAD
+    void* handle = dlopen(path, RTLD_LAZY | RTLD_NOLOAD);
+    if (handle == NULL) {
+        // not being loaded is the expected miss, not a failure
+        dlerror();
+    }
+    return handle;
+#else
+    (void)path;
+    luneffi_set_error("RTLD_NOLOAD is not supported on this platform");
+    return NULL;
+#endif
+}
+
 void* luneffi_dlsym(void* handle, const char* name) {
     luneffi_set_error(NULL);
     void* resolved = dlsym(handle ? handle : RTLD_DEFAULT, name);
@@ -57,6 +94,170 @@ int luneffi_dlclose(void* handle) {
     return rc;
 }
 
+#if defined(LUNEFFI_HAVE_PHDR)
+struct luneffi_callable_query {
+    uintptr_t address;
+    int result;
+};
+
+static int luneffi_check_segments(struct dl_phdr_info* info, size_t size, void* data) {
+    (void)size;
+    struct luneffi_callable_query* query = (struct luneffi_callable_query*)data;
+    for (int index = 0; index < info->dlpi_phnum; index++) {
+        const ElfW(Phdr)* phdr = &info->dlpi_phdr[index];
+        if (phdr->p_type != PT_LOAD) {
+            continue;
+        }
+        uintptr_t start = (uintptr_t)info->dlpi_addr + (uintptr_t)phdr->p_vaddr;
+        if (query->address >= start && query->address < start + (uintptr_t)phdr->p_memsz) {
+            query->result = (phdr->p_flags & PF_X) ? 1 : 0;
+            return 1;
+        }
+    }
+    return 0;
+}
+#endif
+
+int luneffi_is_callable(const void* ptr) {
+    if (ptr == NULL) {
+        return 0;
+    }
+
+#if defined(LUNEFFI_HAVE_PHDR)
+    struct luneffi_callable_query query = { (uintptr_t)ptr, -1 };
+    dl_iterate_phdr(luneffi_check_segments, &query);
+    // addresses outside every loaded object (JIT code, libffi closures) stay unknown
+    return query.result;
+#else
+    Dl_info info;
+    return dladdr(ptr, &info) != 0 ? 1 : -1;
+#endif
+}
+
+#if defined(LUNEFFI_HAVE_DLINFO)
+// glibc rewrites the dynamic section's pointers to absolute addresses while other loaders
+// leave them relative to the load base
+static const void* luneffi_dyn_pointer(const struct link_map* map, ElfW(Addr) value) {
+    if (value < (ElfW(Addr))map->l_addr) {
+        return (const void*)(map->l_addr + value);
+    }
+    return (const void*)value;
+}
+
+// DT_GNU_HASH has no symbol count, so walk the chains to the highest hashed index
+static size_t luneffi_gnu_hash_count(const uint32_t* table) {
+    uint32_t nbuckets = table[0];
+    uint32_t symoffset = table[1];
+    uint32_t bloom_size = table[2];
+    const uint32_t* buckets = (const uint32_t*)((const ElfW(Addr)*)(table + 4) + bloom_size);
+    const uint32_t* chain = buckets + nbuckets;
+
+    uint32_t last = 0;
+    for (uint32_t index = 0; index < nbuckets; index++) {
+        if (buckets[index] > last) {
+            last = buckets[index];
+        }
+    }
+    if (last < symoffset) {
+        return symoffset;
+    }
+    while ((chain[last - symoffset] & 1) == 0) {
+        last++;
+    }
+    return (size_t)last + 1;
+}
+
+static int luneffi_is_exported_kind(int kind) {
+    if (kind == STT_FUNC || kind == STT_OBJECT) {
+        return 1;
+    }
+#if defined(STT_GNU_IFUNC)
+    if (kind == STT_GNU_IFUNC) {
+        return 1;
+    }
+#endif
+    return 0;
+}
+
+int luneffi_exported_symbols(void* handle, luneffi_symbol_visitor visit, void* context) {
+    luneffi_set_error(NULL);
+    // the process handle lists the main program's own exports
+    void* program = NULL;
+    if (handle == NULL) {
+        program = dlopen(NULL, RTLD_LAZY);
+        handle = program;
+    }
+
+    struct link_map* map = NULL;
+    int rc = handle != NULL ? dlinfo(handle, RTLD_DI_LINKMAP, &map) : -1;
+    if (program != NULL) {
+        dlclose(program);
+    }
+    if (rc != 0 || map == NULL) {
+        const char* err = dlerror();
+        luneffi_set_error(err ? err : "dlinfo failed");
+        return -1;
+    }
+
+    const ElfW(Sym)* symtab = NULL;
+    const char* strtab = NULL;
+    size_t count = 0;
+    for (const ElfW(Dyn)* entry = map->l_ld; entry->d_tag != DT_NULL; entry++) {
+        switch (entry->d_tag) {
+        case DT_SYMTAB:
+            symtab = (const ElfW(Sym)*)luneffi_dyn_pointer(map, entry->d_un.d_ptr);
+            break;
+        case DT_STRTAB:
+            strtab = (const char*)luneffi_dyn_pointer(map, entry->d_un.d_ptr);
+            break;
+        case DT_HASH:
+            count = ((const uint32_t*)luneffi_dyn_pointer(map, entry->d_un.d_ptr))[1];
+            break;
+#if defined(DT_GNU_HASH)
+        case DT_GNU_HASH:
+            if (count == 0) {
+                count = luneffi_gnu_hash_count((const uint32_t*)luneffi_dyn_pointer(map, entry->d_un.d_ptr));
+            }
+            break;
+#endif
+        default:
+            break;
+        }
+    }
+    if (symtab == NULL || strtab == NULL || count == 0) {
+        luneffi_set_error("library has no dynamic symbol table");
+        return -1;
+    }
+
+    for (size_t index = 1; index < count; index++) {
+        const ElfW(Sym)* symbol = &symtab[index];
+        int binding = ELF64_ST_BIND(symbol->st_info);
+        int kind = ELF64_ST_TYPE(symbol->st_info);
+        if (symbol->st_shndx == SHN_UNDEF || symbol->st_name == 0) {
+            continue;
+        }
+        if (binding != STB_GLOBAL && binding != STB_WEAK) {
+            continue;
+        }
+        if (!luneffi_is_exported_kind(kind)) {
+            continue;
+        }
+        if (visit(strtab + symbol->st_name, context) != 0) {
+            break;
+        }
+    }
+    return 0;
+}
+#else
+int luneffi_exported_symbols(void* handle, luneffi_symbol_visitor visit, void* context) {
+    (void)handle;
+    (void)visit;
+    (void)context;
+    luneffi_set_error("exported symbol enumeration is unsupported on this platform");
+    return -1;
+}
+#endif
+
 const char* luneffi_dlerror(void) {
     if (luneffi_last_error[0] == '\0') {
         return NULL;
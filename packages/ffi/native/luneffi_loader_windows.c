@@ -77,6 +77,25 @@ void* luneffi_dlopen(const char* path) {
     return handle;
 }
 
+void* luneffi_dlopen_flags(const char* path, int flags) {
+    /* LoadLibrary has no lazy binding mode and no global/local namespace split */
+    (void)flags;
+    return luneffi_dlopen(path);
+}
+
+void* luneffi_dlopen_existing(const char* path) {
+    luneffi_set_error(NULL);
+    HMODULE module = NULL;
+    /* unlike GetModuleHandle this takes a reference, so FreeLibrary balances it */
+    if (!GetModuleHandleExA(0, path, &module)) {
+        if (GetLastError() != ERROR_MOD_NOT_FOUND) {
+            luneffi_capture_last_error("GetModuleHandleExA failed");
+        }
+        return NULL;
+    }
+    return module;
+}
+
 void* luneffi_dlsym(void* handle, const char* name) {
     luneffi_set_error(NULL);
     HMODULE module = (HMODULE)handle;
@@ -113,6 +132,62 @@ int luneffi_dlclose(void* handle) {
     return 0;
 }
 
+int luneffi_is_callable(const void* ptr) {
+    if (ptr == NULL) {
+        return 0;
+    }
+
+    MEMORY_BASIC_INFORMATION info;
+    if (VirtualQuery(ptr, &info, sizeof(info)) == 0) {
+        return -1;
+    }
+    if (info.State != MEM_COMMIT) {
+        return 0;
+    }
+
+    DWORD executable = PAGE_EXECUTE | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+    return (info.Protect & executable) ? 1 : 0;
+}
+
+int luneffi_exported_symbols(void* handle, luneffi_symbol_visitor visit, void* context) {
+    luneffi_set_error(NULL);
+    HMODULE module = (HMODULE)handle;
+    if (module == NULL) {
+        module = GetModuleHandleA(NULL);
+        if (module == NULL) {
+            luneffi_capture_last_error("GetModuleHandleA(NULL)");
+            return -1;
+        }
+    }
+
+    const BYTE* base = (const BYTE*)module;
+    const IMAGE_DOS_HEADER* dos = (const IMAGE_DOS_HEADER*)base;
+    if (dos->e_magic != IMAGE_DOS_SIGNATURE) {
+        luneffi_set_error("module has no DOS header");
+        return -1;
+    }
+    const IMAGE_NT_HEADERS* nt = (const IMAGE_NT_HEADERS*)(base + dos->e_lfanew);
+    if (nt->Signature != IMAGE_NT_SIGNATURE) {
+        luneffi_set_error("module has no PE header");
+        return -1;
+    }
+
+    const IMAGE_DATA_DIRECTORY* directory = &nt->OptionalHeader.DataDirectory[IMAGE_DIRECTORY_ENTRY_EXPORT];
+    if (directory->VirtualAddress == 0 || directory->Size == 0) {
+        // a module without an export directory simply exports nothing
+        return 0;
+    }
+
+    const IMAGE_EXPORT_DIRECTORY* exports = (const IMAGE_EXPORT_DIRECTORY*)(base + directory->VirtualAddress);
+    const DWORD* names = (const DWORD*)(base + exports->AddressOfNames);
+    for (DWORD index = 0; index < exports->NumberOfNames; index++) {
+        if (visit((const char*)(base + names[index]), context) != 0) {
+            break;
+        }
+    }
+    return 0;
+}
+
 const char* luneffi_dlerror(void) {
     if (luneffi_last_error[0] == '\0') {
         return NULL;
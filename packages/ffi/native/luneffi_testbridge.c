@@ -1,8 +1,11 @@
 #include "luneffi_loader.h"
 
+#include <errno.h>
 #include <stdarg.h>
 #include <stddef.h>
 #include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
 
 #if defined(_WIN32)
 #define LUNEFFI_TEST_EXPORT __declspec(dllexport)
@@ -10,6 +13,20 @@
 #define LUNEFFI_TEST_EXPORT __attribute__((visibility("default")))
 #endif
 
+LUNEFFI_TEST_EXPORT int luneffi_test_global_counter = 42;
+LUNEFFI_TEST_EXPORT double luneffi_test_global_ratio = 0.5;
+LUNEFFI_TEST_EXPORT const char* luneffi_test_global_version = "luneffi-test 1.0";
+
+static int luneffi_test_tick_count = 0;
+
+LUNEFFI_TEST_EXPORT void luneffi_test_tick(void) {
+    ++luneffi_test_tick_count;
+}
+
+LUNEFFI_TEST_EXPORT int luneffi_test_ticks(void) {
+    return luneffi_test_tick_count;
+}
+
 LUNEFFI_TEST_EXPORT int luneffi_test_add_ints(int a, int b) {
     return a + b;
 }
@@ -27,6 +44,11 @@ LUNEFFI_TEST_EXPORT int luneffi_test_variadic_sum(int count, ...) {
     return (int)total;
 }
 
+// stands in for a C23 `int f(...)`, which older compilers cannot define
+LUNEFFI_TEST_EXPORT int luneffi_test_variadic_none(void) {
+    return 7;
+}
+
 LUNEFFI_TEST_EXPORT int luneffi_test_variadic_format(char* buffer, size_t size, const char* fmt, ...) {
     if (buffer == NULL || size == 0) {
         return -1;
@@ -39,6 +61,137 @@ LUNEFFI_TEST_EXPORT int luneffi_test_variadic_format(char* buffer, size_t size,
     return written;
 }
 
+LUNEFFI_TEST_EXPORT int luneffi_test_widen_uint8(unsigned char value) {
+    return value;
+}
+
+LUNEFFI_TEST_EXPORT int luneffi_test_fail_with_errno(int code) {
+    if (code != 0) {
+        errno = code;
+        return -1;
+    }
+    return 0;
+}
+
+typedef struct {
+    int a;
+    int b;
+} luneffi_test_int_pair;
+
+typedef struct {
+    double x;
+    double y;
+} luneffi_test_double_pair;
+
+typedef struct {
+    long long a;
+    long long b;
+    long long c;
+} luneffi_test_long_triple;
+
+LUNEFFI_TEST_EXPORT luneffi_test_int_pair luneffi_test_make_int_pair(int a, int b) {
+    luneffi_test_int_pair pair = { a, b };
+    return pair;
+}
+
+LUNEFFI_TEST_EXPORT luneffi_test_double_pair luneffi_test_make_double_pair(double x, double y) {
+    luneffi_test_double_pair pair = { x, y };
+    return pair;
+}
+
+LUNEFFI_TEST_EXPORT luneffi_test_long_triple luneffi_test_make_long_triple(long long a, long long b, long long c) {
+    luneffi_test_long_triple triple = { a, b, c };
+    return triple;
+}
+
+typedef struct {
+    int values[3];
+} luneffi_test_int_vec3;
+
+typedef union {
+    int as_int;
+    float as_float;
+} luneffi_test_int_float;
+
+typedef union {
+    double whole;
+    double halves[2];
+} luneffi_test_double_span;
+
+LUNEFFI_TEST_EXPORT luneffi_test_int_vec3 luneffi_test_make_int_vec3(int a, int b, int c) {
+    luneffi_test_int_vec3 vec = { { a, b, c } };
+    return vec;
+}
+
+LUNEFFI_TEST_EXPORT luneffi_test_int_float luneffi_test_make_int_float(int value) {
+    luneffi_test_int_float result;
+    result.as_int = value;
+    return result;
+}
+
+LUNEFFI_TEST_EXPORT luneffi_test_double_span luneffi_test_make_double_span(double first, double second) {
+    luneffi_test_double_span span;
+    span.halves[0] = first;
+    span.halves[1] = second;
+    return span;
+}
+
+typedef int (*luneffi_pair_compare)(luneffi_test_int_pair, luneffi_test_int_pair);
+
+LUNEFFI_TEST_EXPORT int luneffi_test_compare_pairs(luneffi_pair_compare cmp, int a, int b) {
+    luneffi_test_int_pair left = { a, b };
+    luneffi_test_int_pair right = { b, a };
+    return cmp(left, right);
+}
+
+typedef luneffi_test_int_pair (*luneffi_pair_maker)(int);
+
+LUNEFFI_TEST_EXPORT int luneffi_test_use_made_pair(luneffi_pair_maker make, int seed) {
+    luneffi_test_int_pair pair = make(seed);
+    return pair.a * 10 + pair.b;
+}
+
+typedef luneffi_test_long_triple (*luneffi_triple_maker)(long long);
+
+LUNEFFI_TEST_EXPORT long long luneffi_test_use_made_triple(luneffi_triple_maker make, long long seed) {
+    luneffi_test_long_triple triple = make(seed);
+    return triple.a * 100 + triple.b * 10 + triple.c;
+}
+
+typedef long long (*luneffi_triple_reducer)(luneffi_test_long_triple);
+
+LUNEFFI_TEST_EXPORT long long luneffi_test_reduce_triple(luneffi_triple_reducer reduce, long long a, long long b, long long c) {
+    luneffi_test_long_triple triple = { a, b, c };
+    return reduce(triple);
+}
+
+typedef struct {
+    int value;
+} luneffi_test_counter;
+
+/* stands in for a member function: `this` is the leading argument */
+LUNEFFI_TEST_EXPORT int luneffi_test_counter_add(luneffi_test_counter* self, int delta) {
+    self->value += delta;
+    return self->value;
+}
+
+LUNEFFI_TEST_EXPORT void* luneffi_test_identity_pointer(void* value) {
+    return value;
+}
+
+LUNEFFI_TEST_EXPORT unsigned long long luneffi_test_identity_u64(unsigned long long value) {
+    return value;
+}
+
+// pointer plus out-param length, with an embedded NUL that a C string read would stop at
+LUNEFFI_TEST_EXPORT const char* luneffi_test_sized_data(size_t* out_len) {
+    static const char data[] = {'a', 'b', '\0', 'c', 'd'};
+    if (out_len != NULL) {
+        *out_len = sizeof(data);
+    }
+    return data;
+}
+
 typedef int (*luneffi_unary_callback)(int);
 
 LUNEFFI_TEST_EXPORT int luneffi_test_call_callback(luneffi_unary_callback cb, int value) {
@@ -48,6 +201,29 @@ LUNEFFI_TEST_EXPORT int luneffi_test_call_callback(luneffi_unary_callback cb, in
     return cb(value);
 }
 
+LUNEFFI_TEST_EXPORT int luneffi_test_call_vtable(void** vtable, int slot, int value) {
+    luneffi_unary_callback entry = (luneffi_unary_callback)vtable[slot];
+    return entry(value);
+}
+
+typedef double (*luneffi_double_callback)(double);
+typedef signed char (*luneffi_char_callback)(signed char);
+
+LUNEFFI_TEST_EXPORT double luneffi_test_call_double_callback(luneffi_double_callback cb, double value) {
+    return cb(value);
+}
+
+LUNEFFI_TEST_EXPORT int luneffi_test_call_char_callback(luneffi_char_callback cb, signed char value) {
+    return cb(value);
+}
+
+typedef int (*luneffi_id_callback)(unsigned long long, long long);
+
+/* hands the callback ids a double cannot hold exactly */
+LUNEFFI_TEST_EXPORT int luneffi_test_call_id_callback(luneffi_id_callback cb) {
+    return cb(0xFFFFFFFFFFFFFFFFull, -9007199254740993ll);
+}
+
 typedef struct {
     int x;
     double y;
@@ -92,3 +268,116 @@ LUNEFFI_TEST_EXPORT int luneffi_test_union_is_ptr(const RuntimeTaggedUnion* valu
     }
     return value->as_ptr == ptr;
 }
+
+#if defined(_MSC_VER)
+#pragma pack(push, 1)
+typedef struct {
+    char tag;
+    int value;
+    short count;
+    double ratio;
+} RuntimePackedRecord;
+#pragma pack(pop)
+#else
+typedef struct __attribute__((packed)) {
+    char tag;
+    int value;
+    short count;
+    double ratio;
+} RuntimePackedRecord;
+#endif
+
+LUNEFFI_TEST_EXPORT size_t luneffi_test_packed_layout(int field) {
+    switch (field) {
+    case 1:
+        return offsetof(RuntimePackedRecord, tag);
+    case 2:
+        return offsetof(RuntimePackedRecord, value);
+    case 3:
+        return offsetof(RuntimePackedRecord, count);
+    case 4:
+        return offsetof(RuntimePackedRecord, ratio);
+    default:
+        return sizeof(RuntimePackedRecord);
+    }
+}
+
+#if defined(_MSC_VER)
+#pragma pack(push, 1)
+typedef struct {
+    float x;
+    float y;
+} luneffi_test_packed_float_pair;
+typedef struct {
+    char tag;
+    int value;
+} luneffi_test_packed_tagged;
+#pragma pack(pop)
+#else
+typedef struct __attribute__((packed)) {
+    float x;
+    float y;
+} luneffi_test_packed_float_pair;
+typedef struct __attribute__((packed)) {
+    char tag;
+    int value;
+} luneffi_test_packed_tagged;
+#endif
+
+/* every field keeps its natural offset, so this travels like a plain float pair */
+LUNEFFI_TEST_EXPORT luneffi_test_packed_float_pair luneffi_test_make_packed_float_pair(float x, float y) {
+    luneffi_test_packed_float_pair pair = { x, y };
+    return pair;
+}
+
+/* `value` sits at offset 1, which libffi cannot describe */
+LUNEFFI_TEST_EXPORT luneffi_test_packed_tagged luneffi_test_make_packed_tagged(char tag, int value) {
+    luneffi_test_packed_tagged record = { tag, value };
+    return record;
+}
+
+#if defined(_MSC_VER)
+typedef struct __declspec(align(32)) {
+    char tag;
+    __declspec(align(16)) int value;
+    short count;
+} RuntimeAlignedRecord;
+#else
+typedef struct __attribute__((aligned(32))) {
+    char tag;
+    int value __attribute__((aligned(16)));
+    short count;
+} RuntimeAlignedRecord;
+#endif
+
+typedef struct {
+    char pad;
+    RuntimeAlignedRecord record;
+} RuntimeAlignedProbe;
+
+LUNEFFI_TEST_EXPORT size_t luneffi_test_aligned_layout(int field) {
+    switch (field) {
+    case 1:
+        return offsetof(RuntimeAlignedRecord, tag);
+    case 2:
+        return offsetof(RuntimeAlignedRecord, value);
+    case 3:
+        return offsetof(RuntimeAlignedRecord, count);
+    case 4:
+        return offsetof(RuntimeAlignedProbe, record);
+    default:
+        return sizeof(RuntimeAlignedRecord);
+    }
+}
+
+LUNEFFI_TEST_EXPORT char* luneffi_test_dup_string(const char* value) {
+    if (value == NULL) {
+        return NULL;
+    }
+    size_t length = strlen(value) + 1;
+    char* copy = (char*)malloc(length);
+    if (copy != NULL) {
+        memcpy(copy, value, length);
+    }
+    return copy;
+}